@@ -17,7 +17,7 @@ struct MacroCallback {
 impl ParseCallbacks for MacroCallback {
     fn add_derives(&self, info: &DeriveInfo<'_>) -> Vec<String> {
         match info.name {
-            "HG_Status" | "Radio_MessageType" | "Radio_RobotCommand" =>
+            "HG_Status" | "Radio_MessageType" | "Radio_RobotCommand" | "HG_ConfigOperation" | "HG_VariableType" | "HG_ReflexState" =>
                 vec![
                     "FromPrimitive".into(),
                     "ToPrimitive".into(),
@@ -27,7 +27,7 @@ impl ParseCallbacks for MacroCallback {
             "Radio_PrimaryStatusHF" | "Radio_PrimaryStatusLF" | "Radio_SerialMessage" |
             "Radio_ImuReadings" | "MessageType" | "Radio_Message" |
             "Radio_Message__bindgen_ty_1" | "Radio_Message__bindgen_ty_1__bindgen_ty_1" | "Radio_Message__bindgen_ty_1__bindgen_ty_2" |
-            "Radio_MultiConfigMessage" | "HG_ConfigOperation" | "HG_VariableType" | "Radio_PrimaryStatusHF__bindgen_ty_1" | "Radio_PrimaryStatusHF__bindgen_ty_2" |
+            "Radio_MultiConfigMessage" | "Radio_PrimaryStatusHF__bindgen_ty_1" | "Radio_PrimaryStatusHF__bindgen_ty_2" |
             "Radio_OdometryReading" | "Radio_OverrideOdometry" | "Radio_Access" | 
             "Radio_MessageWrapper" =>
                 vec![
@@ -36,6 +36,8 @@ impl ParseCallbacks for MacroCallback {
             "HG_Variable" =>
                 vec![
                     "EnumIter".into(),
+                    "FromPrimitive".into(),
+                    "ToPrimitive".into(),
                     "AsBytes".into(),
                 ],
             _ => vec![],
@@ -45,16 +47,13 @@ impl ParseCallbacks for MacroCallback {
 
 
 
-fn main() {
-    let macros = Arc::new(RwLock::new(HashSet::new()));
-
+// Shared bindgen settings for every protocol version. The header still has to be added.
+fn bindings_builder(macros: &Arc<RwLock<HashSet<String>>>) -> bindgen::Builder {
     // The bindgen::Builder is the main entry point
     // to bindgen, and lets you build up options for
     // the resulting bindings.
     #[allow(unused_mut)]
     let mut bindgen_builder = bindgen::Builder::default()
-        // The input header we would like to generate
-        // bindings for.
         .raw_line("use num_derive::{ToPrimitive,FromPrimitive};")
         .raw_line("use zerocopy_derive::AsBytes;")
         .raw_line("use strum_macros::EnumIter;")
@@ -63,7 +62,6 @@ fn main() {
         .raw_line("unsafe impl zerocopy::AsBytes for __BindgenBitfieldUnit<[u8; 2]> { fn only_derive_is_allowed_to_implement_this_trait() {} }")
         .raw_line("unsafe impl zerocopy::AsBytes for __BindgenBitfieldUnit<[u8; 4]> { fn only_derive_is_allowed_to_implement_this_trait() {} }")
         .raw_line("unsafe impl zerocopy::AsBytes for __BindgenBitfieldUnit<[u8; 8]> { fn only_derive_is_allowed_to_implement_this_trait() {} }")
-        .header("wrapper.hpp")
         .derive_debug(true)
         .rustified_enum("HG::Status")
         .rustified_enum("CAN::DEVICE_ID")
@@ -97,16 +95,83 @@ fn main() {
         bindgen_builder = bindgen_builder.clang_arg("-D_LIBCPP_HAS_NO_THREADS");
     }
 
-    let bindings = bindgen_builder
+    bindgen_builder
+}
+
+// Older protocol revisions can be checked out next to the main submodule as
+// extern/Firmware_Protocols_v<major>_<minor>. Returns (module name, directory) for each.
+fn legacy_protocol_dirs() -> Vec<(String, PathBuf)> {
+    let mut dirs: Vec<(String, PathBuf)> = std::fs::read_dir("extern")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().join("radio/protocols_radio.h").exists())
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    let version = name.strip_prefix("Firmware_Protocols_")?.to_owned();
+                    let mut parts = version.strip_prefix('v')?.split('_');
+                    let (major, minor) = (parts.next()?, parts.next()?);
+                    if parts.next().is_some() || major.parse::<u8>().is_err() || minor.parse::<u8>().is_err() {
+                        return None;
+                    }
+                    Some((version, entry.path()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+fn main() {
+    let macros = Arc::new(RwLock::new(HashSet::new()));
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+    let bindings = bindings_builder(&macros)
+        // The input header we would like to generate
+        // bindings for.
+        .header("wrapper.hpp")
         // Finish the builder and generate the bindings.
         .generate()
         // Unwrap the Result and panic on failure.
         .expect("Unable to generate bindings");
 
     // Write the bindings to the $OUT_DIR/bindings.rs file.
-    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
     // println!("cargo:warning={}", env::var("OUT_DIR").unwrap());
     bindings
         .write_to_file(out_path.join("bindings.rs"))
         .expect("Couldn't write bindings!");
-}
\ No newline at end of file
+
+    // Generate every legacy protocol version into its own module of glue::protocols
+    println!("cargo:rerun-if-changed=extern");
+    println!("cargo:rerun-if-changed=wrapper.hpp");
+    println!("cargo:rerun-if-changed=build.rs");
+    let wrapper = std::fs::read_to_string("wrapper.hpp").expect("Couldn't read wrapper.hpp");
+    let mut protocols = String::new();
+    let mut codecs = String::new();
+    for (version, dir) in legacy_protocol_dirs() {
+        let dir = std::fs::canonicalize(dir).unwrap();
+        let versioned_wrapper = out_path.join(format!("wrapper_{}.hpp", version));
+        std::fs::write(
+            &versioned_wrapper,
+            wrapper.replace("extern/Firmware_Protocols/", &format!("{}/", dir.display())),
+        )
+        .expect("Couldn't write versioned wrapper!");
+
+        bindings_builder(&macros)
+            .header(versioned_wrapper.to_string_lossy())
+            .generate()
+            .expect("Unable to generate versioned bindings")
+            .write_to_file(out_path.join(format!("bindings_{}.rs", version)))
+            .expect("Couldn't write versioned bindings!");
+
+        protocols += &format!(
+            "pub mod {version} {{\n    include!(concat!(env!(\"OUT_DIR\"), \"/bindings_{version}.rs\"));\n    crate::translated_codec!(Codec);\n}}\n"
+        );
+        codecs += &format!("        Box::new({version}::Codec),\n");
+    }
+    protocols += &format!(
+        "pub fn legacy_codecs() -> Vec<Box<dyn crate::base_station_client::codec::ProtocolCodec>> {{\n    vec![\n{codecs}    ]\n}}\n"
+    );
+    std::fs::write(out_path.join("protocols.rs"), protocols).expect("Couldn't write protocols!");
+}
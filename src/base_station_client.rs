@@ -1,4 +1,30 @@
 pub mod utils;
 pub mod robot;
 pub mod basestation;
-pub mod serial;
\ No newline at end of file
+pub mod serial;
+pub mod config_profile;
+pub mod channel;
+pub mod survey;
+pub mod version;
+pub mod codec;
+pub mod command;
+pub mod scaling;
+pub mod limits;
+pub mod events;
+pub mod field;
+pub mod smart_kick;
+pub mod reflex;
+pub mod odometry;
+pub mod state;
+pub mod health;
+pub mod battery;
+pub mod kinematics;
+pub mod diagnostics;
+pub mod possession;
+pub mod kicker;
+pub mod imu;
+pub mod history;
+pub mod presence;
+pub mod link;
+pub mod latency;
+pub mod tracker;
\ No newline at end of file
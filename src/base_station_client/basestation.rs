@@ -2,9 +2,29 @@
 #[allow(unused_imports)]
 use serialport::Error;
 
+use super::battery::*;
+use super::codec::*;
+use super::command::GlobalCommand;
+use super::config_profile::*;
+use super::events::*;
+use super::field::*;
+use super::health::*;
+use super::history::*;
+use super::imu::*;
+use super::kicker::*;
+use super::latency::*;
+use super::link::*;
+use super::limits::*;
+use super::odometry::*;
+use super::possession::*;
+use super::presence::*;
+use super::reflex::*;
 use super::robot::*;
 use super::serial::*;
+use super::smart_kick::*;
+use super::tracker::*;
 use super::utils::Stamped;
+use super::version::*;
 use crate::glue::*;
 
 use std::num::NonZeroUsize;
@@ -13,18 +33,37 @@ pub const MAX_NUM_ROBOTS: usize = 6;
 
 const DEBUG_SCROLLBACK_LIMIT: usize = 500;
 
+// How often the monitor thread reads the serial port. Frames are timestamped when they are
+// parsed, so the arrival times of frames are only known to about this.
+pub const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(20);
+
+// Entries kept in each of the per-frame buffers of BaseStation until they are drained
+pub const FRAME_BUFFER_LEN: usize = 1024;
+
+// Drop the oldest entries of a buffer beyond FRAME_BUFFER_LEN
+fn cap_frame_buffer<T>(buffer: &mut Vec<T>) {
+    let excess = buffer.len().saturating_sub(FRAME_BUFFER_LEN);
+    buffer.drain(..excess);
+}
+
+// A few vision frames, see Monitor::set_max_vision_heading_age
+pub const DEFAULT_MAX_VISION_HEADING_AGE: std::time::Duration = std::time::Duration::from_millis(100);
+
 pub struct Debug {
     pub incoming_lines:
         std::collections::vec_deque::VecDeque<(chrono::DateTime<chrono::Local>, String, String)>,
+    #[deprecated(note = "use Monitor::imu_history or Monitor::history")]
     pub imu_values: [std::collections::vec_deque::VecDeque<(
         chrono::DateTime<chrono::Local>,
         Radio_ImuReadings,
     )>; MAX_NUM_ROBOTS],
+    #[deprecated(note = "use Monitor::odometry_history or Monitor::history")]
     pub odo_values: [std::collections::vec_deque::VecDeque<(
         chrono::DateTime<chrono::Local>,
         Radio_OdometryReading,
     )>; MAX_NUM_ROBOTS],
     pub config_variable_returns: [[Stamped<u32>; 256]; MAX_NUM_ROBOTS],
+    pub base_config_variable_returns: [Stamped<u32>; 256],
     pub update: bool,
 }
 
@@ -34,21 +73,50 @@ pub struct BaseStation {
     pub base_info: Stamped<Base_Information>,
 
     pub serial: Serial,
+    // The following are filled by read_and_parse and drained by the monitor thread after every
+    // call. Whoever uses a BaseStation directly has to drain them as well, otherwise only the
+    // latest FRAME_BUFFER_LEN entries of each are kept.
+    // Odometry readings received since the monitor thread last took them
+    pub odometry_readings: Vec<OdometrySample>,
+    // IMU readings received since the monitor thread last took them
+    pub imu_readings: Vec<ImuSample>,
+    // Statuses and echoed commands received since the monitor thread last took them, for the history
+    pub history_samples: Vec<(Radio_SSL_ID, std::time::Instant, HistorySample)>,
+    // Frames received since the monitor thread last took them, for the link metrics
+    pub arrivals: Vec<(Radio_SSL_ID, LinkMessage, std::time::Instant)>,
+    // Echoed commands received since the monitor thread last took them, for the round trip latency
+    pub command_echoes: Vec<(Radio_SSL_ID, CommandTag, std::time::Instant)>,
+    codec: Box<dyn ProtocolCodec>,
     start_time: std::time::Instant,
 }
 
 impl Debug {
+    #[allow(deprecated)]
     pub fn new() -> Self {
         Debug {
             incoming_lines: Default::default(),
             imu_values: Default::default(),
             odo_values: Default::default(),
             config_variable_returns: [[Stamped::NothingYet; 256]; MAX_NUM_ROBOTS],
+            base_config_variable_returns: [Stamped::NothingYet; 256],
             update: false,
         }
     }
 }
 
+impl Debug {
+    // Most recent returned value of a config variable, for a robot or the base station
+    pub fn config_return(&self, id: Radio_SSL_ID, var: HG_Variable) -> Stamped<u32> {
+        if id == Radio_BaseStation_ID {
+            return self.base_config_variable_returns[var as usize];
+        }
+        if id as usize >= MAX_NUM_ROBOTS {
+            return Stamped::NothingYet;
+        }
+        self.config_variable_returns[id as usize][var as usize]
+    }
+}
+
 impl Default for Debug {
     fn default() -> Self {
         Self::new()
@@ -63,9 +131,64 @@ impl BaseStation {
             robots: Default::default(),
             base_info: Stamped::NothingYet,
             serial,
+            odometry_readings: vec![],
+            imu_readings: vec![],
+            history_samples: vec![],
+            arrivals: vec![],
+            command_echoes: vec![],
+            codec: Box::new(CurrentCodec),
             start_time,
         })
     }
+
+    // Protocol version currently used to talk to the base station
+    pub fn codec(&self) -> &dyn ProtocolCodec {
+        &*self.codec
+    }
+
+    // Switch to the codec for the given version, if this build supports it
+    fn select_codec(&mut self, version: &HG_Version) {
+        let wanted = (version.protocols_major, version.protocols_minor);
+        if self.codec.protocol_version() == wanted {
+            return;
+        }
+        if let Some(codec) = codec_for(wanted.0, wanted.1) {
+            self.codec = codec;
+        }
+    }
+
+    // Translate a frame into the layout of the current protocol version. Frames no codec knows
+    // are returned unchanged.
+    fn decode_frame(&mut self, data: Vec<u8>) -> Vec<u8> {
+        if let Some(decoded) = self.codec.decode(&data) {
+            return decoded;
+        }
+        // Might be a base station on another protocol version introducing itself
+        match detect_base_information(&*self.codec, &data) {
+            Some((codec, decoded)) => {
+                self.codec = codec;
+                decoded
+            }
+            None => data,
+        }
+    }
+
+    // Encode a message with the current codec and send it
+    pub fn send_message(&mut self, id: Radio_SSL_ID, msg: Radio_Message_Rust) -> Result<(), std::io::Error> {
+        use zerocopy::AsBytes;
+        let mw = Radio_MessageWrapper {
+            id,
+            _pad: [0, 0, 0],
+            msg: msg.wrap(),
+        };
+        match self.codec.encode(mw.as_bytes()) {
+            Some(payload) => self.serial.send_encoded(&payload),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Message not supported by this protocol version",
+            )),
+        }
+    }
     
     pub fn connection_time(&self) -> std::time::Duration {
         self.start_time.elapsed()
@@ -77,6 +200,7 @@ impl BaseStation {
         let mut update_base_info = false;
         loop {
             if let Some(data) = self.serial.read_packet()? {
+                let data = self.decode_frame(data);
                 const LEN_BASE_INFORMATION: usize =
                     std::mem::size_of::<crate::glue::Base_Information>();
                 const LEN_MESSAGE_WRAPER: usize =
@@ -84,6 +208,7 @@ impl BaseStation {
                 match data.len() {
                     LEN_BASE_INFORMATION => {
                         if let Some(base_info) = Base_Information::from_bytes(data) {
+                            self.select_codec(&base_info.version);
                             self.base_info.update(base_info);
                             update_base_info = true;
                             if let Some(&mut ref mut dbg) = debug {
@@ -99,12 +224,44 @@ impl BaseStation {
                     }
                     LEN_MESSAGE_WRAPER => {
                         if let Some(msg) = Radio_MessageWrapper::from_bytes(data) {
+                            if msg.id == Radio_BaseStation_ID {
+                                // The base station only talks to us to return config variables
+                                if let Radio_Message_Rust::MultiConfigMessage(mcm) = Radio_Message_Rust::unwrap(msg.msg) {
+                                    if let Some(&mut ref mut dbg) = debug {
+                                        (*dbg).incoming_lines.push_front((
+                                            chrono::Local::now(),
+                                            format!("B"),
+                                            format!("{:?}", mcm),
+                                        ));
+                                        (*dbg).incoming_lines.truncate(DEBUG_SCROLLBACK_LIMIT);
+                                        (*dbg).update = true;
+                                        match mcm.operation {
+                                            HG_ConfigOperation::READ_RETURN
+                                            | HG_ConfigOperation::WRITE_RETURN
+                                            | HG_ConfigOperation::SET_DEFAULT_RETURN => {
+                                                for i in 0..5 {
+                                                    if mcm.vars[i] == HG_Variable::NONE {
+                                                        continue;
+                                                    }
+                                                    (*dbg).base_config_variable_returns
+                                                        [mcm.vars[i] as usize] =
+                                                        Stamped::make_now(mcm.values[i]);
+                                                }
+                                            }
+                                            _ => (),
+                                        }
+                                    }
+                                }
+                                continue;
+                            }
                             if msg.id as usize >= MAX_NUM_ROBOTS {
                                 continue;
                             } // Invalid robot id, continue to next frame
                             match Radio_Message_Rust::unwrap(msg.msg) {
                                 Radio_Message_Rust::PrimaryStatusHF(status_hf) => {
                                     self.robots[msg.id as usize].update_status_hf(status_hf);
+                                    self.arrivals.push((msg.id, LinkMessage::StatusHF, std::time::Instant::now()));
+                                    self.history_samples.push((msg.id, std::time::Instant::now(), HistorySample::of_status_hf(&status_hf)));
                                     update_robots = true;
                                     if let Some(&mut ref mut dbg) = debug {
                                         (*dbg).incoming_lines.push_front((
@@ -118,6 +275,8 @@ impl BaseStation {
                                 }
                                 Radio_Message_Rust::PrimaryStatusLF(status_lf) => {
                                     self.robots[msg.id as usize].update_status_lf(status_lf);
+                                    self.arrivals.push((msg.id, LinkMessage::StatusLF, std::time::Instant::now()));
+                                    self.history_samples.push((msg.id, std::time::Instant::now(), HistorySample::of_status_lf(&status_lf)));
                                     update_robots = true;
                                     if let Some(&mut ref mut dbg) = debug {
                                         (*dbg).incoming_lines.push_front((
@@ -131,6 +290,9 @@ impl BaseStation {
                                 }
                                 Radio_Message_Rust::Command(command) => {
                                     self.robots[msg.id as usize].update_command(command);
+                                    self.arrivals.push((msg.id, LinkMessage::CommandEcho, std::time::Instant::now()));
+                                    self.command_echoes.push((msg.id, CommandTag::of_command(&command), std::time::Instant::now()));
+                                    self.history_samples.push((msg.id, std::time::Instant::now(), HistorySample::of_command(&command)));
                                     update_robots = true;
                                     if let Some(&mut ref mut dbg) = debug {
                                         (*dbg).incoming_lines.push_front((
@@ -144,6 +306,9 @@ impl BaseStation {
                                 }
                                 Radio_Message_Rust::GlobalCommand(gcommand) => {
                                     self.robots[msg.id as usize].update_global_command(gcommand);
+                                    self.arrivals.push((msg.id, LinkMessage::GlobalCommandEcho, std::time::Instant::now()));
+                                    self.command_echoes.push((msg.id, CommandTag::of_global_command(&gcommand), std::time::Instant::now()));
+                                    self.history_samples.push((msg.id, std::time::Instant::now(), HistorySample::of_global_command(&gcommand)));
                                     update_robots = true;
                                     if let Some(&mut ref mut dbg) = debug {
                                         (*dbg).incoming_lines.push_front((
@@ -157,33 +322,48 @@ impl BaseStation {
                                 }
                                 Radio_Message_Rust::ImuReadings(imu_reading) => {
                                     self.robots[msg.id as usize].update_imu_reading(imu_reading);
+                                    self.arrivals.push((msg.id, LinkMessage::Imu, std::time::Instant::now()));
                                     update_robots = true;
+                                    self.imu_readings.push(ImuSample::new(msg.id, std::time::Instant::now(), &imu_reading));
                                     if let Some(&mut ref mut dbg) = debug {
                                         (*dbg).incoming_lines.push_front((
                                             chrono::Local::now(),
                                             format!("{}", msg.id),
                                             format!("{:?}", imu_reading),
                                         ));
-                                        (*dbg).imu_values[msg.id as usize]
-                                            .push_front((chrono::Local::now(), imu_reading));
-                                        (*dbg).imu_values[msg.id as usize]
-                                            .truncate(DEBUG_SCROLLBACK_LIMIT);
+                                        #[allow(deprecated)]
+                                        {
+                                            (*dbg).imu_values[msg.id as usize]
+                                                .push_front((chrono::Local::now(), imu_reading));
+                                            (*dbg).imu_values[msg.id as usize]
+                                                .truncate(DEBUG_SCROLLBACK_LIMIT);
+                                        }
                                         (*dbg).incoming_lines.truncate(DEBUG_SCROLLBACK_LIMIT);
                                         (*dbg).update = true;
                                     }
                                 }
                                 Radio_Message_Rust::OdometryReading(odo_reading) => {
-                                    // self.robots[msg.id as usize].update_odo_reading(odo_reading);
+                                    self.robots[msg.id as usize].update_odo_reading(odo_reading);
+                                    self.arrivals.push((msg.id, LinkMessage::Odometry, std::time::Instant::now()));
+                                    update_robots = true;
+                                    self.odometry_readings.push(OdometrySample {
+                                        id: msg.id,
+                                        timestamp: std::time::Instant::now(),
+                                        odometry: odo_reading.into(),
+                                    });
                                     if let Some(&mut ref mut dbg) = debug {
                                         (*dbg).incoming_lines.push_front((
                                             chrono::Local::now(),
                                             format!("{}", msg.id),
                                             format!("{:?}", odo_reading),
                                         ));
-                                        (*dbg).odo_values[msg.id as usize]
-                                            .push_front((chrono::Local::now(), odo_reading));
-                                        (*dbg).odo_values[msg.id as usize]
-                                            .truncate(DEBUG_SCROLLBACK_LIMIT);
+                                        #[allow(deprecated)]
+                                        {
+                                            (*dbg).odo_values[msg.id as usize]
+                                                .push_front((chrono::Local::now(), odo_reading));
+                                            (*dbg).odo_values[msg.id as usize]
+                                                .truncate(DEBUG_SCROLLBACK_LIMIT);
+                                        }
                                         (*dbg).incoming_lines.truncate(DEBUG_SCROLLBACK_LIMIT);
                                         (*dbg).update = true;
                                     }
@@ -255,6 +435,11 @@ impl BaseStation {
                 break;
             }
         }
+        cap_frame_buffer(&mut self.odometry_readings);
+        cap_frame_buffer(&mut self.imu_readings);
+        cap_frame_buffer(&mut self.history_samples);
+        cap_frame_buffer(&mut self.arrivals);
+        cap_frame_buffer(&mut self.command_echoes);
         Ok((update_robots, update_base_info))
     }
 } // impl Monitor
@@ -273,11 +458,29 @@ pub struct Monitor {
     base_station_info_channel: ring_channel::RingReceiver<Stamped<Base_Information>>,
     most_recent_base_station_info: Stamped<Base_Information>,
 
+    serial_statistics_channel: ring_channel::RingReceiver<SerialStatistics>,
+    most_recent_serial_statistics: SerialStatistics,
+
     // con_rq: ring_channel::RingSender<String>,
     // con_rq_ack: ring_channel::RingReceiver<String>,
 
     error_receiver: std::sync::mpsc::Receiver<String>,
 
+    version_guard: std::sync::Arc<std::sync::Mutex<VersionGuard>>,
+
+    limiter: std::sync::Mutex<CommandLimiter>,
+    smart_kicks: std::sync::Arc<std::sync::Mutex<SmartKicks>>,
+    reflex_kicks: std::sync::Arc<std::sync::Mutex<ReflexKicks>>,
+    trackers: std::sync::Arc<std::sync::Mutex<Trackers>>,
+    event_sender: ring_channel::RingSender<Event>,
+    event_receiver: ring_channel::RingReceiver<Event>,
+
+    field_frame: std::sync::Mutex<FieldFrame>,
+    // Latest heading from vision per robot, in the team frame
+    vision_headings: std::sync::Mutex<[Stamped<f32>; MAX_NUM_ROBOTS]>,
+    // send_global refuses vision headings older than this
+    max_vision_heading_age: std::sync::Mutex<std::time::Duration>,
+
     bs_connected: ring_channel::RingReceiver<bool>,
     most_recent_bs_connected: bool,
 }
@@ -321,12 +524,14 @@ impl Monitor {
     pub fn start() -> Self {
         let base_station_mux: std::sync::Arc<std::sync::Mutex<Option<BaseStation>>> =
             std::sync::Arc::new(std::sync::Mutex::new(None));
+        #[allow(deprecated)]
         let debug_mux: std::sync::Arc<std::sync::Mutex<Debug>> =
             std::sync::Arc::new(std::sync::Mutex::new(Debug {
                 incoming_lines: Default::default(),
                 imu_values: Default::default(),
                 odo_values: Default::default(),
                 config_variable_returns: [[Stamped::NothingYet; 256]; MAX_NUM_ROBOTS],
+                base_config_variable_returns: [Stamped::NothingYet; 256],
                 update: false,
             }));
 
@@ -339,11 +544,23 @@ impl Monitor {
         
         let (robot_status_sender, robot_status_channel) = ring_channel::ring_channel(NonZeroUsize::new(1).unwrap());
         let (base_station_info_sender, base_station_info_channel) = ring_channel::ring_channel(NonZeroUsize::new(1).unwrap());
+        let (serial_statistics_sender, serial_statistics_channel) = ring_channel::ring_channel(NonZeroUsize::new(1).unwrap());
             
         let (bs_connected_sender, bs_connected) = ring_channel::ring_channel(NonZeroUsize::new(1).unwrap());
+        let (event_sender, event_receiver) = ring_channel::ring_channel(NonZeroUsize::new(EVENT_QUEUE_LEN).unwrap());
+        let thread_event_sender = event_sender.clone();
         
         let (error_sender, error_receiver) = std::sync::mpsc::channel();
 
+        let version_guard: std::sync::Arc<std::sync::Mutex<VersionGuard>> = Default::default();
+        let version_guard_clone = std::sync::Arc::clone(&version_guard);
+        let smart_kicks: std::sync::Arc<std::sync::Mutex<SmartKicks>> = Default::default();
+        let smart_kicks_clone = std::sync::Arc::clone(&smart_kicks);
+        let reflex_kicks: std::sync::Arc<std::sync::Mutex<ReflexKicks>> = Default::default();
+        let reflex_kicks_clone = std::sync::Arc::clone(&reflex_kicks);
+        let trackers: std::sync::Arc<std::sync::Mutex<Trackers>> = Default::default();
+        let trackers_clone = std::sync::Arc::clone(&trackers);
+
         // let (con_rq, con_rq_rec) = ring_channel::ring_channel(NonZeroUsize::new(1).unwrap());
         // let (con_rq_ack_send, con_rq_ack) = ring_channel::ring_channel(NonZeroUsize::new(1).unwrap());
         
@@ -363,20 +580,112 @@ impl Monitor {
                         let debug = &mut *debug_mut;
                         match base_station.read_and_parse(Some(debug)) {
                             Ok((update_robots, update_base_info)) => {
+                                if let Ok(mut trackers) = trackers_clone.lock() {
+                                    let trackers = &mut *trackers;
+                                    let config = &trackers.config;
+                                    for (id, message, time) in base_station.arrivals.drain(..) {
+                                        trackers.links.received(&config.link, id, message, time);
+                                    }
+                                    for (id, tag, time) in base_station.command_echoes.drain(..) {
+                                        trackers.latencies.echoed(&config.latency, id, tag, time);
+                                    }
+                                    for (id, time, sample) in base_station.history_samples.drain(..) {
+                                        trackers.history.record(&config.history, id, time, sample);
+                                    }
+                                    if !base_station.imu_readings.is_empty() {
+                                        for sample in base_station.imu_readings.drain(..) {
+                                            trackers.history.record_imu(&config.history, &sample);
+                                            for event in trackers.imus.push(&config.imu, sample) {
+                                                let _ = thread_event_sender.send(Event::Imu(event));
+                                            }
+                                        }
+                                        trackers.imus.apply(&mut base_station.robots);
+                                    }
+                                    if update_robots {
+                                        let robots = &mut base_station.robots;
+                                        for event in trackers.diagnostics.update(&config.diagnostics, robots) {
+                                            let _ = thread_event_sender.send(Event::Motor(event));
+                                        }
+                                        for warning in trackers.batteries.update(&config.battery, robots) {
+                                            let _ = thread_event_sender.send(Event::Battery(warning));
+                                        }
+                                        for event in trackers.possessions.update(&config.possession, robots) {
+                                            let _ = thread_event_sender.send(Event::Possession(event));
+                                        }
+                                        for kick in trackers.kickers.update(&config.kicker, robots) {
+                                            let _ = thread_event_sender.send(Event::Kick(kick));
+                                        }
+                                    }
+                                    // Only the latest reading of every robot is sent as an event, the
+                                    // others are in the history
+                                    let mut latest_odometry: [Option<OdometrySample>; MAX_NUM_ROBOTS] = [None; MAX_NUM_ROBOTS];
+                                    for reading in base_station.odometry_readings.drain(..) {
+                                        trackers.history.record_odometry(&config.history, &reading);
+                                        if let Some(status) = trackers.odometry_overrides.check(config.odometry_tolerance, &reading) {
+                                            let _ = thread_event_sender.send(Event::OdometryOverride(status));
+                                        }
+                                        if let Some(latest) = latest_odometry.get_mut(reading.id as usize) {
+                                            *latest = Some(reading);
+                                        }
+                                    }
+                                    for reading in latest_odometry.into_iter().flatten() {
+                                        let _ = thread_event_sender.send(Event::Odometry(reading));
+                                    }
+                                }
                                 if update_robots {
                                     let _ = robot_status_sender.send(base_station.robots);
+                                    if let Ok(mut smart_kicks) = smart_kicks_clone.lock() {
+                                        smart_kicks.update_from_robots(&base_station.robots);
+                                    }
+                                    if let Ok(mut reflex_kicks) = reflex_kicks_clone.lock() {
+                                        for event in reflex_kicks.update_from_robots(&base_station.robots) {
+                                            let _ = thread_event_sender.send(Event::ReflexKick(event));
+                                        }
+                                    }
                                 }
                                 if update_base_info {
                                     let _ = base_station_info_sender.send(base_station.base_info);
+                                    let mut guard = version_guard_clone.lock().unwrap();
+                                    if let (true, Stamped::Have(_, base_info)) = (guard.handshake.is_pending(), base_station.base_info) {
+                                        guard.handshake = ProtocolHandshake::check(base_info.version);
+                                        if guard.handshake.is_mismatched() && guard.policy != VersionPolicy::Allow {
+                                            let _ = error_sender.send(mismatch_message(&base_info.version));
+                                        }
+                                    }
                                 }
                             },
                             Err(_) => disconnect = true,
                         };
+                        if let Ok(mut trackers) = trackers_clone.lock() {
+                            let trackers = &mut *trackers;
+                            let config = &trackers.config;
+                            let now = std::time::Instant::now();
+                            trackers.latencies.expire(&config.latency, now);
+                            let events = trackers.presences.update(&config.presence, &mut base_station.robots, now);
+                            // Robots time out without new frames, the monitor has to see them go offline
+                            if !events.is_empty() {
+                                let _ = robot_status_sender.send(base_station.robots);
+                            }
+                            for event in events {
+                                let _ = thread_event_sender.send(Event::Presence(event));
+                            }
+                            let batteries = trackers.batteries.map(BatteryTracker::estimate);
+                            for alert in trackers.health.update(&config.health, &base_station.robots, &batteries, now) {
+                                let _ = thread_event_sender.send(Event::Health(alert));
+                            }
+                        }
+                        let _ = serial_statistics_sender.send(base_station.serial.statistics());
                         for _ in 0..2 { // Limit how often this can run
                             match global_command_receiver.try_recv() {
-                                Ok((id, command)) => {
-                                    match base_station.serial.send_global_command(id,command) {
-                                        Ok(_) => (),
+                                Ok((id, mut command)) => {
+                                    let tag = trackers_clone.lock().ok().and_then(|mut trackers| trackers.latencies.tag_global_command(id, &mut command));
+                                    match base_station.send_message(id, Radio_Message_Rust::GlobalCommand(command)) {
+                                        Ok(_) => {
+                                            if let (Some(tag), Ok(mut trackers)) = (tag, trackers_clone.lock()) {
+                                                let trackers = &mut *trackers;
+                                                trackers.latencies.sent(&trackers.config.latency, id, tag, std::time::Instant::now());
+                                            }
+                                        },
                                         Err(_) => {
                                             error_sender.send("Error transmitting command".to_owned()).unwrap();
                                             let _ = bs_connected_sender.send(false);
@@ -393,9 +702,15 @@ impl Monitor {
                         }
                         for _ in 0..2 { // Limit how often this can run
                             match command_receiver.try_recv() {
-                                Ok((id, command)) => {
-                                    match base_station.serial.send_command(id,command) {
-                                        Ok(_) => (),
+                                Ok((id, mut command)) => {
+                                    let tag = trackers_clone.lock().ok().and_then(|mut trackers| trackers.latencies.tag_command(id, &mut command));
+                                    match base_station.send_message(id, Radio_Message_Rust::Command(command)) {
+                                        Ok(_) => {
+                                            if let (Some(tag), Ok(mut trackers)) = (tag, trackers_clone.lock()) {
+                                                let trackers = &mut *trackers;
+                                                trackers.latencies.sent(&trackers.config.latency, id, tag, std::time::Instant::now());
+                                            }
+                                        },
                                         Err(_) => {
                                             error_sender.send("Error transmitting command".to_owned()).unwrap();
                                             let _ = bs_connected_sender.send(false);
@@ -412,7 +727,7 @@ impl Monitor {
                         }
                         match message_receiver.try_recv() {
                             Ok((id, msg)) => {
-                                match base_station.serial.send_message(id,msg) {
+                                match base_station.send_message(id, msg) {
                                     Ok(_) => (),
                                     Err(_) => {
                                         error_sender.send("Error transmitting message".to_owned()).unwrap();
@@ -427,7 +742,7 @@ impl Monitor {
                             Err(std::sync::mpsc::TryRecvError::Empty) => (),
                         }
                         let time_start = std::time::Instant::now();
-                        while time_start.elapsed() < std::time::Duration::from_millis(20) {  }  // Blocking sleep alternative
+                        while time_start.elapsed() < POLL_INTERVAL {  }  // Blocking sleep alternative
                         // std::thread::sleep(std::time::Duration::from_micros(1)); // Give other threads an opportunity to access the mutex
                     } else {
                         let _ = bs_connected_sender.send(false);
@@ -451,7 +766,19 @@ impl Monitor {
             most_recent_robot_status: [Robot::default(); MAX_NUM_ROBOTS],
             base_station_info_channel,
             most_recent_base_station_info: Stamped::NothingYet,
+            serial_statistics_channel,
+            most_recent_serial_statistics: SerialStatistics::default(),
             error_receiver,
+            version_guard,
+            limiter: std::sync::Mutex::new(CommandLimiter::default()),
+            smart_kicks,
+            reflex_kicks,
+            trackers,
+            event_sender,
+            event_receiver,
+            field_frame: std::sync::Mutex::new(FieldFrame::default()),
+            vision_headings: std::sync::Mutex::new([Stamped::NothingYet; MAX_NUM_ROBOTS]),
+            max_vision_heading_age: std::sync::Mutex::new(DEFAULT_MAX_VISION_HEADING_AGE),
             bs_connected,
             most_recent_bs_connected: false,
             // con_rq,
@@ -479,6 +806,14 @@ impl Monitor {
         self.most_recent_base_station_info
     }
 
+    // Get the packet and CRC error counts of the current serial connection
+    pub fn serial_statistics(&mut self) -> SerialStatistics {
+        if let Ok(fresh_statistics) = self.serial_statistics_channel.try_recv() {
+            self.most_recent_serial_statistics = fresh_statistics;
+        }
+        self.most_recent_serial_statistics
+    }
+
     // Get base station connection duration
     pub fn base_connection_duration(&self) -> Option<std::time::Duration> {
         // if let Some(base_station) = &mut self.get_base_station_mux() {
@@ -497,6 +832,143 @@ impl Monitor {
         Some(self.most_recent_robot_status)
     }
 
+    // Lock the trackers of the monitor thread
+    fn trackers(&self) -> Option<std::sync::MutexGuard<'_, Trackers>> {
+        self.trackers.lock().ok()
+    }
+
+    pub fn config(&self) -> MonitorConfig {
+        self.trackers().map(|trackers| trackers.config.clone()).unwrap_or_default()
+    }
+
+    // Change the configuration robots are evaluated with, takes effect from the next status, e.g.
+    // monitor.configure(|config| config.battery.cells = 4)
+    pub fn configure(&self, change: impl FnOnce(&mut MonitorConfig)) {
+        if let Some(mut trackers) = self.trackers() {
+            change(&mut trackers.config);
+        }
+    }
+
+    // Odometry readings of a robot received after since (all that are kept if None), oldest first
+    pub fn odometry_history(
+        &self,
+        id: crate::glue::Radio_SSL_ID,
+        since: Option<std::time::Instant>,
+    ) -> Vec<(std::time::Instant, Odometry)> {
+        self.trackers().map(|trackers| trackers.history.odometry(id, since)).unwrap_or_default()
+    }
+
+    // Query the recorded history of a robot, e.g.
+    // monitor.history(id, |history| history.status_hf.values(from, to, |status| status.motor_speeds))
+    pub fn history<R>(
+        &self,
+        id: crate::glue::Radio_SSL_ID,
+        query: impl FnOnce(&RobotHistory) -> R,
+    ) -> Option<R> {
+        Some(query(self.trackers()?.history.robot(id)?))
+    }
+
+    // IMU readings of a robot received after since (all that are kept if None), oldest first
+    pub fn imu_history(
+        &self,
+        id: crate::glue::Radio_SSL_ID,
+        since: Option<std::time::Instant>,
+    ) -> Vec<ImuSample> {
+        self.trackers().map(|trackers| trackers.history.imu(id, since)).unwrap_or_default()
+    }
+
+    // Reset the gyro integrated heading of a robot, see Robot::orientation
+    pub fn set_imu_heading(&self, id: crate::glue::Radio_SSL_ID, heading: f32) -> Result<(), ()> {
+        self.trackers().ok_or(())?.imus.set_heading(id, heading)
+    }
+
+    // Filtered battery state of a robot
+    pub fn battery(&self, id: crate::glue::Radio_SSL_ID) -> Option<BatteryEstimate> {
+        self.trackers()?.batteries.get(id)?.estimate()
+    }
+
+    // Capacitor charge of a robot, None until it reported its capacitor voltage
+    pub fn kicker(&self, id: crate::glue::Radio_SSL_ID) -> Option<KickerEstimate> {
+        self.trackers()?.kickers.get(id)?.estimate()
+    }
+
+    // Whether the capacitor of a robot is charged enough to kick and the kicker reports no fault
+    pub fn kick_ready(&self, id: crate::glue::Radio_SSL_ID) -> bool {
+        self.kicker(id).is_some_and(|kicker| kicker.kick_ready())
+    }
+
+    // Debounced ball possession of a robot, fused from the breakbeam and ToF sensor
+    pub fn possession(&self, id: crate::glue::Radio_SSL_ID) -> Option<PossessionEstimate> {
+        Some(self.trackers()?.possessions.get(id)?.estimate())
+    }
+
+    // Reception metrics of a robot, computed over the window in LinkConfig up to now
+    pub fn link_quality(&self, id: crate::glue::Radio_SSL_ID) -> Option<LinkQuality> {
+        let trackers = self.trackers()?;
+        trackers.links.quality(&trackers.config.link, id, std::time::Instant::now())
+    }
+
+    // Round trip latency of the commands sent to a robot, matched with their echoes
+    pub fn command_latency(&self, id: crate::glue::Radio_SSL_ID) -> Option<LatencyStats> {
+        self.trackers()?.latencies.stats(id)
+    }
+
+    // Whether a robot is online, stale, offline or rejoined, with its uptime and dropouts
+    pub fn presence(&self, id: crate::glue::Radio_SSL_ID) -> Option<Presence> {
+        self.trackers()?.presences.presence(id)
+    }
+
+    // Latest health of a robot, evaluated with the current health rules
+    pub fn health(&self, id: crate::glue::Radio_SSL_ID) -> Option<Health> {
+        self.trackers()?.health.health(id)
+    }
+
+    // Result of checking the base station protocol version, reset on every connect
+    pub fn protocol_handshake(&self) -> ProtocolHandshake {
+        self.version_guard.lock().map(|guard| guard.handshake).unwrap_or_default()
+    }
+
+    pub fn version_policy(&self) -> VersionPolicy {
+        self.version_guard.lock().map(|guard| guard.policy).unwrap_or_default()
+    }
+
+    // Set what happens when the base station uses a different protocol version
+    pub fn set_version_policy(&self, policy: VersionPolicy) {
+        if let Ok(mut guard) = self.version_guard.lock() {
+            guard.policy = policy;
+        }
+    }
+
+    // Err if the version policy doesn't allow sending anything right now
+    fn check_protocol(&self) -> Result<(), ()> {
+        let guard = self.version_guard.lock().map_err(|_| ())?;
+        if guard.handshake.allows_sending(guard.policy) { Ok(()) } else { Err(()) }
+    }
+
+    // Next event in the order they happened, None if there is nothing new
+    pub fn poll_event(&self) -> Option<Event> {
+        self.event_receiver.try_recv().ok()
+    }
+
+    pub fn command_limits(&self) -> CommandLimits {
+        self.limiter.lock().map(|limiter| limiter.limits).unwrap_or_default()
+    }
+
+    // Set the limits checked for every outgoing command, violations are reported as events
+    pub fn set_command_limits(&self, limits: CommandLimits) {
+        if let Ok(mut limiter) = self.limiter.lock() {
+            limiter.limits = limits;
+        }
+    }
+
+    // Report violations and return the command to send, if any
+    fn limit<T>(&self, checked: Checked<T>) -> Result<T, ()> {
+        for violation in checked.violations {
+            let _ = self.event_sender.send(Event::LimitViolation(violation));
+        }
+        checked.command.ok_or(())
+    }
+
     // Send command to robot
     pub fn send(
         &self,
@@ -516,7 +988,15 @@ impl Monitor {
         id: crate::glue::Radio_SSL_ID,
         command: crate::glue::Radio_Command,
     ) -> Result<(), ()> {
+        self.check_protocol()?;
+        let mut command = command;
+        self.reflex_kicks.lock().map_err(|_| ())?.apply(id, &mut command.gen_command);
+        self.smart_kicks.lock().map_err(|_| ())?.apply(id, &mut command.gen_command);
+        let checked = self.limiter.lock().map_err(|_| ())?.check_command(id, command, std::time::Instant::now());
+        let command = self.limit(checked)?;
         self.send_command_channel.send((id, command)).map_err(|_| ())?;
+        self.reflex_kicks.lock().map_err(|_| ())?.sent(id, &command.gen_command, std::time::Instant::now());
+        self.smart_kicks.lock().map_err(|_| ())?.sent(id, &command.gen_command);
         Ok(())
     }
 
@@ -526,15 +1006,123 @@ impl Monitor {
         id: crate::glue::Radio_SSL_ID,
         gcommand: crate::glue::Radio_GlobalCommand,
     ) -> Result<(), ()> {
+        self.check_protocol()?;
+        let mut gcommand = gcommand;
+        self.reflex_kicks.lock().map_err(|_| ())?.apply(id, &mut gcommand.gen_command);
+        self.smart_kicks.lock().map_err(|_| ())?.apply(id, &mut gcommand.gen_command);
+        let checked = self.limiter.lock().map_err(|_| ())?.check_global_command(id, gcommand, std::time::Instant::now());
+        let gcommand = self.limit(checked)?;
         self.send_global_command_channel.send((id, gcommand)).map_err(|_| ())?;
+        self.reflex_kicks.lock().map_err(|_| ())?.sent(id, &gcommand.gen_command, std::time::Instant::now());
+        self.smart_kicks.lock().map_err(|_| ())?.sent(id, &gcommand.gen_command);
+        Ok(())
+    }
+
+    // Kick after delay. The kick goes out with the next command sent to the robot that passes the
+    // command limits, the handle resolves when the robot reports it or times out. Err if the
+    // delay is too long.
+    pub fn smart_kick(
+        &self,
+        id: crate::glue::Radio_SSL_ID,
+        params: KickParams,
+        delay: std::time::Duration,
+    ) -> Result<SmartKickHandle, ()> {
+        self.smart_kicks.lock().map_err(|_| ())?.start(id, params, delay).ok_or(())
+    }
+
+    // Arm reflex mode, params.robot_command being the command that arms it. The arm command goes
+    // out with the following commands sent to the robot, until it kicked or is disarmed.
+    pub fn arm_reflex_kick(
+        &self,
+        id: crate::glue::Radio_SSL_ID,
+        params: KickParams,
+    ) -> Result<(), ()> {
+        self.reflex_kicks.lock().map_err(|_| ())?.arm(id, params, std::time::Instant::now())
+    }
+
+    // Disarm reflex mode, robot_command being the command that disarms it. The disarm command goes
+    // out with the following commands sent to the robot, until it reports being disarmed.
+    pub fn disarm_reflex_kick(
+        &self,
+        id: crate::glue::Radio_SSL_ID,
+        robot_command: crate::glue::Radio_RobotCommand,
+    ) -> Result<(), ()> {
+        self.reflex_kicks.lock().map_err(|_| ())?.disarm(id, robot_command, std::time::Instant::now())
+    }
+
+    pub fn reflex_kick_status(&self, id: crate::glue::Radio_SSL_ID) -> Option<ReflexKickStatus> {
+        self.reflex_kicks.lock().ok()?.status(id)
+    }
+
+    // The latest reflex phase changes of a robot, oldest first
+    pub fn reflex_kick_transitions(&self, id: crate::glue::Radio_SSL_ID) -> Vec<ReflexTransition> {
+        self.reflex_kicks.lock().map(|reflex_kicks| reflex_kicks.transitions(id)).unwrap_or_default()
+    }
+
+    pub fn field_frame(&self) -> FieldFrame {
+        self.field_frame.lock().map(|frame| *frame).unwrap_or_default()
+    }
+
+    // Set which half of the field we defend, used by send_global
+    pub fn set_field_side(&self, side: FieldSide) {
+        if let Ok(mut frame) = self.field_frame.lock() {
+            frame.side = side;
+        }
+    }
+
+    // Latest heading of a robot from vision in rad, in the team frame
+    pub fn set_vision_heading(
+        &self,
+        id: crate::glue::Radio_SSL_ID,
+        heading: f32,
+    ) -> Result<(), ()> {
+        let mut headings = self.vision_headings.lock().map_err(|_| ())?;
+        headings.get_mut(id as usize).ok_or(())?.update(heading);
         Ok(())
     }
 
+    pub fn vision_heading(&self, id: crate::glue::Radio_SSL_ID) -> Stamped<f32> {
+        self.vision_headings.lock().ok()
+            .and_then(|headings| headings.get(id as usize).copied())
+            .unwrap_or(Stamped::NothingYet)
+    }
+
+    pub fn max_vision_heading_age(&self) -> std::time::Duration {
+        self.max_vision_heading_age.lock().map(|age| *age).unwrap_or(DEFAULT_MAX_VISION_HEADING_AGE)
+    }
+
+    // How old the vision heading may be for send_global to use it
+    pub fn set_max_vision_heading_age(&self, age: std::time::Duration) {
+        if let Ok(mut max_age) = self.max_vision_heading_age.lock() {
+            *max_age = age;
+        }
+    }
+
+    // Send a team frame command to a single robot, with the latest vision heading as the heading
+    // measurement. Err if no heading was set for the robot, or it is older than
+    // max_vision_heading_age(), the robot would turn towards a stale heading.
+    pub fn send_global(
+        &self,
+        id: crate::glue::Radio_SSL_ID,
+        command: &GlobalCommand,
+    ) -> Result<(), ()> {
+        let max_age = self.max_vision_heading_age();
+        let heading = match self.vision_heading(id) {
+            Stamped::Have(time, heading) if time.elapsed() <= max_age => heading,
+            _ => return Err(()),
+        };
+        self.send_single_global(id, command.to_global_command(self.field_frame(), heading))
+    }
+
     // Send command to all robots
     pub fn send_broadcast(
         &self,
         command : crate::glue::Radio_Command,
     ) -> Result<(), ()> {
+        self.check_protocol()?;
+        // Acceleration isn't tracked for broadcasts, every robot may be driving differently
+        let checked = self.limiter.lock().map_err(|_| ())?.check_command(Radio_Broadcast_ID, command, std::time::Instant::now());
+        let command = self.limit(checked)?;
         self.send_command_channel.send((Radio_Broadcast_ID, command)).map_err(|_| ())?;
         Ok(())
     }
@@ -544,6 +1132,7 @@ impl Monitor {
         id: crate::glue::Radio_SSL_ID,
         mcm: crate::glue::Radio_MultiConfigMessage,
     ) -> Result<(), ()> {
+        self.check_protocol()?;
         self.send_message_channel.send((id, Radio_Message_Rust::MultiConfigMessage(mcm))).map_err(|_| ())?;
         Ok(())
     }
@@ -562,39 +1151,189 @@ impl Monitor {
         self.send_mcm(crate::glue::Radio_BaseStation_ID, mcm)
     }
 
+    // Value of a config variable returned by a robot (or the base station) at or after since
+    pub(crate) fn config_return_since(
+        &self,
+        id: crate::glue::Radio_SSL_ID,
+        var: HG_Variable,
+        since: std::time::Instant,
+    ) -> Option<u32> {
+        match self.get_debug_mux()?.config_return(id, var) {
+            Stamped::Have(timestamp, value) if timestamp >= since => Some(value),
+            _ => None,
+        }
+    }
+
+    // Send a config message to a robot (or the base station) and block until every variable in
+    // it has been returned
+    pub(crate) fn config_transaction(
+        &self,
+        id: crate::glue::Radio_SSL_ID,
+        mcm: crate::glue::Radio_MultiConfigMessage,
+        timeout: std::time::Duration,
+    ) -> Result<Vec<(HG_Variable, u32)>, ProfileError> {
+        let vars: Vec<HG_Variable> = mcm.vars.iter().copied().filter(|&v| v != HG_Variable::NONE).collect();
+        if id as usize >= MAX_NUM_ROBOTS && id != Radio_BaseStation_ID { return Err(ProfileError::SendFailed); }
+        let sent_at = std::time::Instant::now();
+        self.send_mcm(id, mcm).map_err(|_| ProfileError::SendFailed)?;
+
+        loop {
+            let mut missing = vec![];
+            let mut returned = vec![];
+            for &var in &vars {
+                match self.config_return_since(id, var, sent_at) {
+                    Some(value) => returned.push((var, value)),
+                    None => missing.push(var),
+                }
+            }
+            if missing.is_empty() {
+                return Ok(returned);
+            }
+            if sent_at.elapsed() > timeout {
+                return Err(ProfileError::Timeout(missing));
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+
+    // Read every configuration variable of a robot into a profile
+    pub fn read_profile(
+        &self,
+        id: crate::glue::Radio_SSL_ID,
+        timeout: std::time::Duration,
+    ) -> Result<ConfigProfile, ProfileError> {
+        let mut profile = ConfigProfile::new(Some(id));
+        (profile.protocols_major, profile.protocols_minor) = self.protocol_in_use();
+        for chunk in profile_variables().chunks(5) {
+            let mut mcm = Radio_MultiConfigMessage::read();
+            for &var in chunk {
+                mcm.add(var, 0);
+            }
+            for (var, value) in self.config_transaction(id, mcm, timeout)? {
+                profile.set(var, value);
+            }
+        }
+        Ok(profile)
+    }
+
+    // Protocol version the robots are talked to with, the base station's once it has been seen
+    fn protocol_in_use(&self) -> (u8, u8) {
+        self.protocol_handshake().version()
+            .map_or((crate::glue::CONST_PROTOCOL_VERSION_MAJOR, crate::glue::CONST_PROTOCOL_VERSION_MINOR),
+                |version| (version.protocols_major, version.protocols_minor))
+    }
+
+    // Write a profile to a robot and verify it by reading all variables back. If set_default is
+    // true, the written values are made the robot's defaults afterwards. Profiles read with a
+    // different protocol version are refused, variable numbers and meanings may have changed.
+    pub fn write_profile(
+        &self,
+        id: crate::glue::Radio_SSL_ID,
+        profile: &ConfigProfile,
+        set_default: bool,
+        timeout: std::time::Duration,
+    ) -> Result<(), ProfileError> {
+        if profile.protocol_version() != self.protocol_in_use() {
+            return Err(ProfileError::ProtocolMismatch(profile.protocol_version(), self.protocol_in_use()));
+        }
+        for chunk in profile.values().chunks(5) {
+            let mut mcm = Radio_MultiConfigMessage::write();
+            for &(var, value) in chunk {
+                mcm.add(var, value);
+            }
+            self.config_transaction(id, mcm, timeout)?;
+        }
+
+        let read_back = self.read_profile(id, timeout)?;
+        let mismatches: Vec<(HG_Variable, u32, u32)> = profile.values().iter()
+            .filter_map(|&(var, value)| match read_back.get(var) {
+                Some(actual) if actual == value => None,
+                actual => Some((var, value, actual.unwrap_or(0))),
+            })
+            .collect();
+        if !mismatches.is_empty() {
+            return Err(ProfileError::VerifyFailed(mismatches));
+        }
+
+        if set_default {
+            for chunk in profile.values().chunks(5) {
+                let mut mcm = Radio_MultiConfigMessage::set_default();
+                for &(var, value) in chunk {
+                    mcm.add(var, value);
+                }
+                self.config_transaction(id, mcm, timeout)?;
+            }
+        }
+        Ok(())
+    }
+
     // Send heading override
     pub fn set_current_heading(
         &self,
         id: crate::glue::Radio_SSL_ID,
         heading_rad: f32,
     ) -> Result<(), ()> {
-        self.send_message_channel.send((id, Radio_Message_Rust::OverrideOdometry(
-            Radio_OverrideOdometry{
-                _pad: [0; 12],
-                _pad0: 0,
-                pos_x: 0.0,
-                pos_y: 0.0,
-                ang_z: heading_rad,
-                set_pos_x: false,
-                set_pos_y: false,
-                set_ang_z: true,
-            }
-        ))).map_err(|_| ())?;
+        self.override_odometry(id, OdometryOverride::new().heading(heading_rad))
+    }
+
+    // Set any combination of odometry x, y and heading of a robot. Subsequent odometry readings
+    // are checked against it, see odometry_override_status. Broadcasts are sent but not checked.
+    pub fn override_odometry(
+        &self,
+        id: crate::glue::Radio_SSL_ID,
+        target: OdometryOverride,
+    ) -> Result<(), ()> {
+        if target.is_empty() { return Err(()); }
+        self.send_over_odo(id, target.to_message())?;
+        self.trackers().ok_or(())?.odometry_overrides.sent(id, target, std::time::Instant::now());
         Ok(())
     }
 
+    // Result of checking the latest override of a robot against its odometry
+    pub fn odometry_override_status(&self, id: crate::glue::Radio_SSL_ID) -> Option<OverrideStatus> {
+        self.trackers()?.odometry_overrides.status(id, std::time::Instant::now())
+    }
+
+    // Latest pose of a robot from vision, x and y in m and heading in rad, in the team frame.
+    // Also sets the vision heading and overrides the odometry if auto override is due.
+    pub fn set_vision_pose(
+        &self,
+        id: crate::glue::Radio_SSL_ID,
+        x: f32,
+        y: f32,
+        heading: f32,
+    ) -> Result<(), ()> {
+        self.set_vision_heading(id, heading)?;
+        let frame = self.field_frame();
+        let (x, y) = frame.to_vision(x, y);
+        let heading = frame.heading_to_vision(heading);
+        let target = {
+            let mut trackers = self.trackers().ok_or(())?;
+            let auto = trackers.config.auto_odometry_override;
+            trackers.odometry_overrides.auto_override(auto, id, x, y, heading, std::time::Instant::now())
+        };
+        match target {
+            Some(target) => self.override_odometry(id, target),
+            None => Ok(()),
+        }
+    }
+
     // Send odometry override
     pub fn send_over_odo(
         &self,
         id: u8,
         over_odo: crate::glue::Radio_OverrideOdometry,
     ) -> Result<(), ()> {
+        self.check_protocol()?;
         self.send_message_channel.send((id, Radio_Message_Rust::OverrideOdometry(over_odo))).map_err(|_| ())?;
         Ok(())
     }
 
     // Connect to a base station over a serial COM port
     pub fn connect_to(&self, port: &str) -> Result<(), ()> {
+        if let Ok(mut guard) = self.version_guard.lock() {
+            guard.handshake = ProtocolHandshake::Pending;
+        }
         if let Some(base_station) = &mut self.get_base_station_mux() {
             **base_station = BaseStation::new(port)
                 .map_err(|e| eprintln!("Error connecting to {}: {:?}", port, e))
@@ -0,0 +1,252 @@
+use super::robot::Robot;
+use super::tracker::{LastSeen, RobotTracker};
+use crate::glue::Radio_SSL_ID;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Chemistry {
+    #[default]
+    LiPo,
+    LiFePo4,
+}
+
+impl Chemistry {
+    // Open circuit voltage of a single cell at 0%, 10%, ..., 100% charge [V]
+    pub fn ocv_curve(&self) -> [f32; 11] {
+        match self {
+            Self::LiPo => [3.27, 3.69, 3.73, 3.77, 3.80, 3.84, 3.87, 3.95, 4.02, 4.11, 4.20],
+            Self::LiFePo4 => [2.50, 3.00, 3.20, 3.22, 3.25, 3.26, 3.27, 3.30, 3.32, 3.35, 3.40],
+        }
+    }
+
+    // State of charge (0 to 1) of a cell at rest with the given voltage
+    pub fn state_of_charge(&self, cell_voltage: f32) -> f32 {
+        let curve = self.ocv_curve();
+        if cell_voltage <= curve[0] { return 0.0; }
+        for (i, pair) in curve.windows(2).enumerate() {
+            if cell_voltage <= pair[1] {
+                let fraction = (cell_voltage - pair[0]) / (pair[1] - pair[0]);
+                return (i as f32 + fraction) / 10.0;
+            }
+        }
+        1.0
+    }
+}
+
+// How the left and right pack are connected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PackLayout {
+    // Each pack carries half the current
+    #[default]
+    Parallel,
+    // Both packs carry the full current
+    Series,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatteryConfig {
+    pub chemistry: Chemistry,
+    pub layout: PackLayout,
+    // Cells in series per pack
+    pub cells: u8,
+    // Per pack [Ah]
+    pub capacity: f32,
+    // Per pack [Ohm]
+    pub internal_resistance: f32,
+    // Time constant of the low pass filter on voltages and current
+    pub filter_time_constant: std::time::Duration,
+    // Warn below this state of charge (0 to 1)
+    pub low: f32,
+    pub critical: f32,
+    // Warn if the packs differ more than this [V]
+    pub imbalance: f32,
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        BatteryConfig {
+            chemistry: Chemistry::LiPo,
+            layout: PackLayout::Parallel,
+            cells: 4,
+            capacity: 2.2,
+            internal_resistance: 0.05,
+            filter_time_constant: std::time::Duration::from_secs(5),
+            low: 0.2,
+            critical: 0.1,
+            imbalance: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatteryWarning {
+    Low,
+    Critical,
+    Imbalance,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BatteryEstimate {
+    // Of the emptiest pack (0 to 1)
+    pub state_of_charge: f32,
+    // Left and right (0 to 1)
+    pub pack_state_of_charge: [f32; 2],
+    // Filtered and compensated for the load, left and right [V]
+    pub open_circuit_voltages: [f32; 2],
+    // Filtered current drawn from the battery [A]
+    pub current: f32,
+    // At the current draw, None while nothing is drawn
+    pub remaining: Option<std::time::Duration>,
+    // Difference between the packs [V]
+    pub imbalance: f32,
+}
+
+impl BatteryEstimate {
+    pub fn warnings(&self, config: &BatteryConfig) -> Vec<BatteryWarning> {
+        let mut warnings = vec![];
+        if self.state_of_charge < config.critical {
+            warnings.push(BatteryWarning::Critical);
+        } else if self.state_of_charge < config.low {
+            warnings.push(BatteryWarning::Low);
+        }
+        if self.imbalance > config.imbalance {
+            warnings.push(BatteryWarning::Imbalance);
+        }
+        warnings
+    }
+}
+
+// Sent as an event when a battery warning starts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatteryWarningEvent {
+    pub id: Radio_SSL_ID,
+    pub warning: BatteryWarning,
+    pub estimate: BatteryEstimate,
+}
+
+// Filtered battery state of one robot
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BatteryModel {
+    // (time, left [V], right [V], current [A])
+    filtered: Option<(std::time::Instant, f32, f32, f32)>,
+    estimate: Option<BatteryEstimate>,
+}
+
+impl BatteryModel {
+    // pack_voltages [V] as measured under a total current [A]
+    pub fn update(&mut self, config: &BatteryConfig, pack_voltages: [f32; 2], current: f32, now: std::time::Instant) -> BatteryEstimate {
+        let current = current.max(0.0);
+        let pack_current = match config.layout {
+            PackLayout::Parallel => current / 2.0,
+            PackLayout::Series => current,
+        };
+        let [left, right] = pack_voltages.map(|voltage| voltage + pack_current * config.internal_resistance);
+
+        let (left, right, current) = match self.filtered {
+            Some((time, last_left, last_right, last_current)) => {
+                let dt = now.saturating_duration_since(time).as_secs_f32();
+                let tau = config.filter_time_constant.as_secs_f32();
+                let alpha = if tau > 0.0 { 1.0 - (-dt / tau).exp() } else { 1.0 };
+                let lowpass = |last: f32, new: f32| last + alpha * (new - last);
+                (lowpass(last_left, left), lowpass(last_right, right), lowpass(last_current, current))
+            }
+            None => (left, right, current),
+        };
+        self.filtered = Some((now, left, right, current));
+
+        let cells = config.cells.max(1) as f32;
+        let pack_state_of_charge = [left, right].map(|voltage| config.chemistry.state_of_charge(voltage / cells));
+        let state_of_charge = pack_state_of_charge[0].min(pack_state_of_charge[1]);
+        let charge = match config.layout {
+            PackLayout::Parallel => pack_state_of_charge.iter().sum::<f32>() * config.capacity,
+            PackLayout::Series => state_of_charge * config.capacity,
+        };
+        let remaining = (current > 0.01).then(|| std::time::Duration::from_secs_f32(charge / current * 3600.0));
+
+        let estimate = BatteryEstimate {
+            state_of_charge,
+            pack_state_of_charge,
+            open_circuit_voltages: [left, right],
+            current,
+            remaining,
+            imbalance: (left - right).abs(),
+        };
+        self.estimate = Some(estimate);
+        estimate
+    }
+
+    pub fn estimate(&self) -> Option<BatteryEstimate> {
+        self.estimate
+    }
+}
+
+// Battery model of a robot, kept by the monitor thread
+#[derive(Debug, Default)]
+pub(crate) struct BatteryTracker {
+    model: BatteryModel,
+    last_status: LastSeen,
+}
+
+impl BatteryTracker {
+    pub fn estimate(&self) -> Option<BatteryEstimate> {
+        self.model.estimate()
+    }
+}
+
+impl RobotTracker for BatteryTracker {
+    type Config = BatteryConfig;
+    type Event = BatteryWarningEvent;
+
+    // Update the model with a new PrimaryStatusLF, returns warnings that just started
+    fn update(&mut self, config: &BatteryConfig, id: Radio_SSL_ID, robot: &mut Robot) -> Vec<BatteryWarningEvent> {
+        let (Some(pack_voltages), Some(main_board_current)) = (robot.pack_voltages(), robot.main_board_current()) else { return vec![]; };
+        let Some(received) = self.last_status.take_new(robot.status_lf_timestamp()) else { return vec![]; };
+
+        let motor_current: f32 = robot.motor_currents().map_or(0.0, |currents| currents.iter().map(|c| c.abs()).sum());
+        let before = self.model.estimate().map_or(vec![], |estimate| estimate.warnings(config));
+        let estimate = self.model.update(config, pack_voltages, main_board_current + motor_current, received);
+        estimate.warnings(config).into_iter()
+            .filter(|warning| !before.contains(warning))
+            .map(|warning| BatteryWarningEvent { id, warning, estimate })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod battery_tests {
+    use super::*;
+
+    #[test]
+    fn state_of_charge() {
+        let lipo = Chemistry::LiPo;
+        assert_eq!(lipo.state_of_charge(4.3), 1.0);
+        assert_eq!(lipo.state_of_charge(3.0), 0.0);
+        assert!((lipo.state_of_charge(3.84) - 0.5).abs() < 1e-5);
+        assert!((lipo.state_of_charge(3.86) - 0.5667).abs() < 1e-3);
+        assert!(Chemistry::LiFePo4.state_of_charge(3.26) < lipo.state_of_charge(4.0));
+    }
+
+    #[test]
+    fn model() {
+        let config = BatteryConfig::default();
+        let start = std::time::Instant::now();
+        let mut model = BatteryModel::default();
+
+        // 10 A drawn, 5 A per pack drops 0.25 V per pack
+        let estimate = model.update(&config, [15.11, 15.11], 10.0, start);
+        assert!((estimate.open_circuit_voltages[0] - 15.36).abs() < 1e-4);
+        assert!((estimate.state_of_charge - 0.5).abs() < 1e-3);
+        let remaining = estimate.remaining.unwrap().as_secs_f32();
+        assert!((remaining - 2.2 * 3600.0 / 10.0).abs() < 10.0);
+        assert!(estimate.warnings(&config).is_empty());
+
+        // A sudden dip is filtered
+        let estimate = model.update(&config, [13.0, 13.0], 10.0, start + std::time::Duration::from_millis(100));
+        assert!(estimate.state_of_charge > 0.45);
+
+        // Imbalance and a low battery
+        let mut model = BatteryModel::default();
+        let estimate = model.update(&config, [14.9, 14.5], 0.0, start);
+        assert_eq!(estimate.remaining, None);
+        assert_eq!(estimate.warnings(&config), vec![BatteryWarning::Critical, BatteryWarning::Imbalance]);
+    }
+}
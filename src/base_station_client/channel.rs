@@ -0,0 +1,220 @@
+use super::basestation::{Monitor, MAX_NUM_ROBOTS};
+use super::config_profile::ProfileError;
+use super::survey::SimulatedBaseStation;
+use crate::glue::{HG_Variable, Radio_BaseStation_ID, Radio_MultiConfigMessage, Radio_SSL_ID};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelSwitchError {
+    // The monitor could not queue a message for the base station
+    SendFailed,
+    // The base station did not return the RADIO_CHANNEL variable
+    BaseStationNoReply,
+    // These robots did not show up on the new channel. If rolled_back is true, the base station
+    // is back on the old channel and every robot that was online was heard there again.
+    RobotsLost { lost: Vec<Radio_SSL_ID>, rolled_back: bool },
+}
+
+impl From<ProfileError> for ChannelSwitchError {
+    fn from(err: ProfileError) -> Self {
+        match err {
+            ProfileError::Timeout(_) => ChannelSwitchError::BaseStationNoReply,
+            _ => ChannelSwitchError::SendFailed,
+        }
+    }
+}
+
+// The steps of a channel switch, implemented by the monitor and the simulated base station
+pub trait ChannelRadio {
+    // Current channel of the base station
+    fn base_channel(&mut self, timeout: std::time::Duration) -> Result<u8, ChannelSwitchError>;
+    fn online_robots(&mut self) -> Vec<Radio_SSL_ID>;
+    // Send a RADIO_CHANNEL write to every robot, without waiting for the returns
+    fn send_robot_channels(&mut self, ids: &[Radio_SSL_ID], chan: u8) -> Result<(), ChannelSwitchError>;
+    // Wait for the WRITE_RETURNs of the last send_robot_channels, returns the robots that didn't confirm
+    fn await_robot_returns(&mut self, ids: &[Radio_SSL_ID], timeout: std::time::Duration) -> Vec<Radio_SSL_ID>;
+    // Move the base station and wait for its WRITE_RETURN
+    fn write_base_channel(&mut self, chan: u8, timeout: std::time::Duration) -> Result<(), ChannelSwitchError>;
+    // Wait until every robot was heard after the last write_base_channel, returns the ones that weren't
+    fn await_robots(&mut self, ids: &[Radio_SSL_ID], timeout: std::time::Duration) -> Vec<Radio_SSL_ID>;
+}
+
+// Move all online robots and then the base station to a new radio channel. The robots are all
+// sent the new channel at once and their WRITE_RETURNs waited for together, then the base station
+// follows, and every robot that was online has to reappear on the new channel within the timeout.
+// If one doesn't, the robots are sent the old channel and the base station moves back after them,
+// the rollback succeeded if every robot is heard on the old channel again.
+pub fn switch_channel<R: ChannelRadio>(radio: &mut R, chan: u8, timeout: std::time::Duration) -> Result<(), ChannelSwitchError> {
+    let old_chan = radio.base_channel(timeout)?;
+    if old_chan == chan {
+        return Ok(());
+    }
+
+    // A robot that doesn't confirm may still have switched (only the return got lost), so
+    // don't give up here and let the rejoin check below decide
+    let online = radio.online_robots();
+    radio.send_robot_channels(&online, chan)?;
+    radio.await_robot_returns(&online, timeout);
+    radio.write_base_channel(chan, timeout)?;
+
+    let lost = radio.await_robots(&online, timeout);
+    if lost.is_empty() {
+        return Ok(());
+    }
+
+    // The returns of the robots going back are sent on the old channel, so they aren't waited for
+    radio.send_robot_channels(&online, old_chan)?;
+    let base_station_back = radio.write_base_channel(old_chan, timeout).is_ok();
+    let rolled_back = base_station_back && radio.await_robots(&online, timeout).is_empty();
+    Err(ChannelSwitchError::RobotsLost { lost, rolled_back })
+}
+
+// Switches channels with the real radios
+struct MonitorChannels<'a> {
+    monitor: &'a mut Monitor,
+    robots_written: std::time::Instant,
+    base_station_written: std::time::Instant,
+}
+
+impl ChannelRadio for MonitorChannels<'_> {
+    fn base_channel(&mut self, timeout: std::time::Duration) -> Result<u8, ChannelSwitchError> {
+        self.monitor.read_channel(timeout)
+    }
+
+    fn online_robots(&mut self) -> Vec<Radio_SSL_ID> {
+        let robots = self.monitor.get_robots().unwrap_or_default();
+        (0..MAX_NUM_ROBOTS)
+            .filter(|&i| robots[i].is_online())
+            .map(|i| i as Radio_SSL_ID)
+            .collect()
+    }
+
+    fn send_robot_channels(&mut self, ids: &[Radio_SSL_ID], chan: u8) -> Result<(), ChannelSwitchError> {
+        self.robots_written = std::time::Instant::now();
+        for &id in ids {
+            let mcm = Radio_MultiConfigMessage::write().add(HG_Variable::RADIO_CHANNEL, chan as u32);
+            self.monitor.send_mcm(id, mcm).map_err(|_| ChannelSwitchError::SendFailed)?;
+        }
+        Ok(())
+    }
+
+    fn await_robot_returns(&mut self, ids: &[Radio_SSL_ID], timeout: std::time::Duration) -> Vec<Radio_SSL_ID> {
+        let mut missing = ids.to_vec();
+        loop {
+            missing.retain(|&id| self.monitor.config_return_since(id, HG_Variable::RADIO_CHANNEL, self.robots_written).is_none());
+            if missing.is_empty() || self.robots_written.elapsed() > timeout {
+                return missing;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+
+    fn write_base_channel(&mut self, chan: u8, timeout: std::time::Duration) -> Result<(), ChannelSwitchError> {
+        let mcm = Radio_MultiConfigMessage::write().add(HG_Variable::RADIO_CHANNEL, chan as u32);
+        self.monitor.config_transaction(Radio_BaseStation_ID, mcm, timeout)?;
+        self.base_station_written = std::time::Instant::now();
+        Ok(())
+    }
+
+    fn await_robots(&mut self, ids: &[Radio_SSL_ID], timeout: std::time::Duration) -> Vec<Radio_SSL_ID> {
+        let mut missing = ids.to_vec();
+        loop {
+            let robots = self.monitor.get_robots().unwrap_or_default();
+            let since = self.base_station_written.elapsed();
+            missing.retain(|&id| robots[id as usize].time_since_update().is_none_or(|t| t >= since));
+            if missing.is_empty() || self.base_station_written.elapsed() > timeout {
+                return missing;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+    }
+}
+
+impl Monitor {
+    // Current radio channel of the base station
+    pub fn read_channel(&self, timeout: std::time::Duration) -> Result<u8, ChannelSwitchError> {
+        let mcm = Radio_MultiConfigMessage::read().add(HG_Variable::RADIO_CHANNEL, 0);
+        let returned = self.config_transaction(Radio_BaseStation_ID, mcm, timeout)?;
+        Ok(returned[0].1 as u8)
+    }
+
+    // Move all online robots and the base station to a new radio channel. See switch_channel()
+    pub fn switch_channel(&mut self, chan: u8, timeout: std::time::Duration) -> Result<(), ChannelSwitchError> {
+        let now = std::time::Instant::now();
+        switch_channel(&mut MonitorChannels { monitor: self, robots_written: now, base_station_written: now }, chan, timeout)
+    }
+}
+
+// Frames get through instantly, so the timeouts are never waited for
+impl ChannelRadio for SimulatedBaseStation {
+    fn base_channel(&mut self, _timeout: std::time::Duration) -> Result<u8, ChannelSwitchError> {
+        Ok(self.channel)
+    }
+
+    fn online_robots(&mut self) -> Vec<Radio_SSL_ID> {
+        self.robots.keys().copied().filter(|&id| self.reaches(id)).collect()
+    }
+
+    fn send_robot_channels(&mut self, ids: &[Radio_SSL_ID], chan: u8) -> Result<(), ChannelSwitchError> {
+        self.returned = ids.iter().copied().filter(|&id| self.write_robot_channel(id, chan)).collect();
+        Ok(())
+    }
+
+    fn await_robot_returns(&mut self, ids: &[Radio_SSL_ID], _timeout: std::time::Duration) -> Vec<Radio_SSL_ID> {
+        ids.iter().copied().filter(|id| !self.returned.contains(id)).collect()
+    }
+
+    fn write_base_channel(&mut self, chan: u8, _timeout: std::time::Duration) -> Result<(), ChannelSwitchError> {
+        self.channel = chan;
+        Ok(())
+    }
+
+    fn await_robots(&mut self, ids: &[Radio_SSL_ID], _timeout: std::time::Duration) -> Vec<Radio_SSL_ID> {
+        ids.iter().copied().filter(|&id| !self.reaches(id)).collect()
+    }
+}
+
+#[cfg(test)]
+mod channel_tests {
+    use super::*;
+    use super::super::survey::ChannelConditions;
+
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_millis(100);
+
+    fn channels(station: &SimulatedBaseStation) -> Vec<u8> {
+        station.robots.values().map(|robot| robot.channel).collect()
+    }
+
+    #[test]
+    fn switch() {
+        let mut station = SimulatedBaseStation::new(1, 50.0).with_robot(0).with_robot(2).with_robot(4);
+        assert_eq!(switch_channel(&mut station, 7, TIMEOUT), Ok(()));
+        assert_eq!(station.channel, 7);
+        assert_eq!(channels(&station), vec![7, 7, 7]);
+    }
+
+    #[test]
+    fn rollback() {
+        let mut station = SimulatedBaseStation::new(1, 50.0).with_robot(0).with_stuck_robot(2);
+        assert_eq!(
+            switch_channel(&mut station, 7, TIMEOUT),
+            Err(ChannelSwitchError::RobotsLost { lost: vec![2], rolled_back: true })
+        );
+        assert_eq!(station.channel, 1);
+        assert_eq!(channels(&station), vec![1, 1]);
+    }
+
+    #[test]
+    fn lost_robots() {
+        // Robot 0 follows to channel 7, but nothing gets through there
+        let mut station = SimulatedBaseStation::new(1, 50.0)
+            .with_robot(0)
+            .with_robot(2)
+            .with_conditions(7, ChannelConditions { loss: 1.0, ..Default::default() });
+        assert_eq!(
+            switch_channel(&mut station, 7, TIMEOUT),
+            Err(ChannelSwitchError::RobotsLost { lost: vec![0, 2], rolled_back: false })
+        );
+        assert_eq!(station.channel, 1);
+        assert_eq!(channels(&station), vec![7, 7]);
+    }
+}
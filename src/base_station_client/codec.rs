@@ -0,0 +1,483 @@
+use crate::glue::*;
+
+// Translates frames between one Firmware_Protocols version and the current one, on the byte level.
+// To support an older version, check it out as extern/Firmware_Protocols_v<major>_<minor>. build.rs
+// then generates crate::glue::protocols::v<major>_<minor> with a translated_codec! in it and
+// registers that codec in codecs().
+pub trait ProtocolCodec: Send + std::fmt::Debug {
+    // (protocols_major, protocols_minor)
+    fn protocol_version(&self) -> (u8, u8);
+    // Bring a frame from the base station into the layout of the current version, None if this
+    // version doesn't know the frame
+    fn decode(&self, data: &[u8]) -> Option<Vec<u8>>;
+    // Bring a message wrapper of the current version into this version's layout, None if it
+    // can't be expressed there. The result is the payload of a packet, without start byte,
+    // length and CRC.
+    fn encode(&self, data: &[u8]) -> Option<Vec<u8>>;
+}
+
+// Where the fields glue needs are in the frames of one protocol version, see wire_layout!
+#[derive(Debug, Clone)]
+pub struct WireLayout {
+    pub base_information_len: usize,
+    // HG_Version inside Base_Information, (offset, length)
+    pub version: (usize, usize),
+    pub wrapper_len: usize,
+    pub id_offset: usize,
+    pub message_type_offset: usize,
+    pub payload_offset: usize,
+    // (message type name, message type value, payload length)
+    pub messages: Vec<(&'static str, u8, usize)>,
+    // Every field glue uses inside a payload, see wire_field!
+    pub fields: Vec<WireField>,
+    // (enum name, variant name, value) of every enum that is sent as a field
+    pub enums: Vec<(&'static str, String, u8)>,
+    // HG_ReflexState bitfield inside Radio_PrimaryStatusHF, (offset, [(variant name, bytes of
+    // the bitfield with only this variant set)])
+    pub reflex_state: (usize, Vec<(String, Vec<u8>)>),
+}
+
+// (message type name, field name, offset in the payload, length, enum name if every byte of the
+// field is one value of that enum)
+pub type WireField = (&'static str, &'static str, usize, usize, Option<&'static str>);
+
+pub fn field_len<T, F>(_: fn(&T) -> &F) -> usize {
+    std::mem::size_of::<F>()
+}
+
+// (name, variant name, value) of every variant of a #[repr(u8)] enum
+pub fn enum_variants<E: num_traits::FromPrimitive + std::fmt::Debug>(name: &'static str) -> impl Iterator<Item = (&'static str, String, u8)> {
+    (0..=u8::MAX).filter_map(move |value| Some((name, format!("{:?}", E::from_u8(value)?), value)))
+}
+
+// A WireField of a message payload, e.g. wire_field!("Command", Radio_Command, gen_command.robot_command as "Radio_RobotCommand")
+#[macro_export]
+macro_rules! wire_field {
+    ($message:literal, $payload:ty, $($field:ident).+ $(as $kind:literal)?) => {
+        (
+            $message,
+            stringify!($($field).+),
+            std::mem::offset_of!($payload, $($field).+),
+            $crate::base_station_client::codec::field_len(|payload: &$payload| &payload.$($field).+),
+            None $(.or(Some($kind)))?,
+        )
+    };
+}
+
+// The WireLayout of the bindings in scope, either crate::glue or a glue::protocols module
+#[macro_export]
+macro_rules! wire_layout {
+    () => {{
+        let message_type = |name: &str| {
+            (0..=u8::MAX).find(|&mt| {
+                <Radio_MessageType as num_traits::FromPrimitive>::from_u8(mt)
+                    .is_some_and(|t| format!("{:?}", t) == name)
+            })
+        };
+        let messages = [
+            ("Command", std::mem::size_of::<Radio_Command>()),
+            ("GlobalCommand", std::mem::size_of::<Radio_GlobalCommand>()),
+            ("ImuReadings", std::mem::size_of::<Radio_ImuReadings>()),
+            ("MultiConfigMessage", std::mem::size_of::<Radio_MultiConfigMessage>()),
+            ("PrimaryStatusHF", std::mem::size_of::<Radio_PrimaryStatusHF>()),
+            ("PrimaryStatusLF", std::mem::size_of::<Radio_PrimaryStatusLF>()),
+            ("OdometryReading", std::mem::size_of::<Radio_OdometryReading>()),
+            ("OverrideOdometry", std::mem::size_of::<Radio_OverrideOdometry>()),
+            ("SerialMessage", std::mem::size_of::<Radio_SerialMessage>()),
+        ];
+        $crate::base_station_client::codec::WireLayout {
+            base_information_len: std::mem::size_of::<Base_Information>(),
+            version: (std::mem::offset_of!(Base_Information, version), std::mem::size_of::<HG_Version>()),
+            wrapper_len: std::mem::size_of::<Radio_MessageWrapper>(),
+            id_offset: std::mem::offset_of!(Radio_MessageWrapper, id),
+            message_type_offset: std::mem::offset_of!(Radio_MessageWrapper, msg) + std::mem::offset_of!(Radio_Message, mt),
+            payload_offset: std::mem::offset_of!(Radio_MessageWrapper, msg) + std::mem::offset_of!(Radio_Message, msg),
+            messages: messages
+                .iter()
+                .filter_map(|&(name, len)| Some((name, message_type(name)?, len)))
+                .collect(),
+            fields: vec![
+                $crate::wire_field!("Command", Radio_Command, speed),
+                $crate::wire_field!("Command", Radio_Command, gen_command.dribbler_speed_i),
+                $crate::wire_field!("Command", Radio_Command, gen_command.kick_time_i),
+                $crate::wire_field!("Command", Radio_Command, gen_command.time_to_kick),
+                $crate::wire_field!("Command", Radio_Command, gen_command.smart_kick_couter),
+                $crate::wire_field!("Command", Radio_Command, gen_command.robot_command as "Radio_RobotCommand"),
+                $crate::wire_field!("GlobalCommand", Radio_GlobalCommand, global_speed_x),
+                $crate::wire_field!("GlobalCommand", Radio_GlobalCommand, global_speed_y),
+                $crate::wire_field!("GlobalCommand", Radio_GlobalCommand, heading_last_measurement),
+                $crate::wire_field!("GlobalCommand", Radio_GlobalCommand, heading_setpoint),
+                $crate::wire_field!("GlobalCommand", Radio_GlobalCommand, gen_command.dribbler_speed_i),
+                $crate::wire_field!("GlobalCommand", Radio_GlobalCommand, gen_command.kick_time_i),
+                $crate::wire_field!("GlobalCommand", Radio_GlobalCommand, gen_command.time_to_kick),
+                $crate::wire_field!("GlobalCommand", Radio_GlobalCommand, gen_command.smart_kick_couter),
+                $crate::wire_field!("GlobalCommand", Radio_GlobalCommand, gen_command.robot_command as "Radio_RobotCommand"),
+                $crate::wire_field!("GlobalCommand", Radio_GlobalCommand, max_yaw_rate),
+                $crate::wire_field!("GlobalCommand", Radio_GlobalCommand, preferred_rotation_direction),
+                $crate::wire_field!("ImuReadings", Radio_ImuReadings, ang_wx),
+                $crate::wire_field!("ImuReadings", Radio_ImuReadings, ang_wy),
+                $crate::wire_field!("ImuReadings", Radio_ImuReadings, ang_wz),
+                $crate::wire_field!("ImuReadings", Radio_ImuReadings, ang_x),
+                $crate::wire_field!("ImuReadings", Radio_ImuReadings, ang_y),
+                $crate::wire_field!("ImuReadings", Radio_ImuReadings, ang_z),
+                $crate::wire_field!("MultiConfigMessage", Radio_MultiConfigMessage, vars as "HG_Variable"),
+                $crate::wire_field!("MultiConfigMessage", Radio_MultiConfigMessage, operation as "HG_ConfigOperation"),
+                $crate::wire_field!("MultiConfigMessage", Radio_MultiConfigMessage, type_ as "HG_VariableType"),
+                $crate::wire_field!("MultiConfigMessage", Radio_MultiConfigMessage, values),
+                $crate::wire_field!("PrimaryStatusHF", Radio_PrimaryStatusHF, motor_speeds_i),
+                $crate::wire_field!("PrimaryStatusHF", Radio_PrimaryStatusHF, motor_currents_i),
+                $crate::wire_field!("PrimaryStatusHF", Radio_PrimaryStatusHF, breakbeam_raw),
+                $crate::wire_field!("PrimaryStatusHF", Radio_PrimaryStatusHF, smart_kick_counter_return),
+                $crate::wire_field!("PrimaryStatusHF", Radio_PrimaryStatusHF, kick_counter),
+                $crate::wire_field!("PrimaryStatusHF", Radio_PrimaryStatusHF, tof_ball_x),
+                $crate::wire_field!("PrimaryStatusHF", Radio_PrimaryStatusHF, tof_ball_y),
+                $crate::wire_field!("PrimaryStatusHF", Radio_PrimaryStatusHF, tof_confidence),
+                $crate::wire_field!("PrimaryStatusHF", Radio_PrimaryStatusHF, __bindgen_anon_1),
+                $crate::wire_field!("PrimaryStatusLF", Radio_PrimaryStatusLF, pack_voltages),
+                $crate::wire_field!("PrimaryStatusLF", Radio_PrimaryStatusLF, motor_driver_temps),
+                $crate::wire_field!("PrimaryStatusLF", Radio_PrimaryStatusLF, cap_voltage),
+                $crate::wire_field!("PrimaryStatusLF", Radio_PrimaryStatusLF, main_board_current),
+                $crate::wire_field!("PrimaryStatusLF", Radio_PrimaryStatusLF, avg_loop_time),
+                $crate::wire_field!("PrimaryStatusLF", Radio_PrimaryStatusLF, max_loop_time),
+                $crate::wire_field!("PrimaryStatusLF", Radio_PrimaryStatusLF, avg_command_time),
+                $crate::wire_field!("PrimaryStatusLF", Radio_PrimaryStatusLF, primary_status as "HG_Status"),
+                $crate::wire_field!("PrimaryStatusLF", Radio_PrimaryStatusLF, kicker_status as "HG_Status"),
+                $crate::wire_field!("PrimaryStatusLF", Radio_PrimaryStatusLF, tof_status as "HG_Status"),
+                $crate::wire_field!("PrimaryStatusLF", Radio_PrimaryStatusLF, imu_status as "HG_Status"),
+                $crate::wire_field!("PrimaryStatusLF", Radio_PrimaryStatusLF, motor_status as "HG_Status"),
+                $crate::wire_field!("OdometryReading", Radio_OdometryReading, pos_x),
+                $crate::wire_field!("OdometryReading", Radio_OdometryReading, pos_y),
+                $crate::wire_field!("OdometryReading", Radio_OdometryReading, ang_z),
+                $crate::wire_field!("OverrideOdometry", Radio_OverrideOdometry, pos_x),
+                $crate::wire_field!("OverrideOdometry", Radio_OverrideOdometry, pos_y),
+                $crate::wire_field!("OverrideOdometry", Radio_OverrideOdometry, ang_z),
+                $crate::wire_field!("OverrideOdometry", Radio_OverrideOdometry, set_pos_x),
+                $crate::wire_field!("OverrideOdometry", Radio_OverrideOdometry, set_pos_y),
+                $crate::wire_field!("OverrideOdometry", Radio_OverrideOdometry, set_ang_z),
+                $crate::wire_field!("SerialMessage", Radio_SerialMessage, data),
+            ],
+            enums: std::iter::empty()
+                .chain($crate::base_station_client::codec::enum_variants::<HG_Status>("HG_Status"))
+                .chain($crate::base_station_client::codec::enum_variants::<Radio_RobotCommand>("Radio_RobotCommand"))
+                .chain($crate::base_station_client::codec::enum_variants::<HG_ConfigOperation>("HG_ConfigOperation"))
+                .chain($crate::base_station_client::codec::enum_variants::<HG_VariableType>("HG_VariableType"))
+                .chain($crate::base_station_client::codec::enum_variants::<HG_Variable>("HG_Variable"))
+                .collect(),
+            reflex_state: (
+                std::mem::offset_of!(Radio_PrimaryStatusHF, __bindgen_anon_1),
+                (0..=u8::MAX)
+                    .filter_map(<HG_ReflexState as num_traits::FromPrimitive>::from_u8)
+                    .map(|state| {
+                        // The bitfield struct only holds the bitfield unit, all zeroes is valid
+                        let mut flags: Radio_PrimaryStatusHF__bindgen_ty_1 = unsafe { std::mem::zeroed() };
+                        flags.set_reflex_state(state);
+                        (format!("{:?}", state), zerocopy::AsBytes::as_bytes(&flags).to_vec())
+                    })
+                    .collect(),
+            ),
+        }
+    }};
+}
+
+// Defines a codec for the bindings in scope, which translates their frames to and from
+// CurrentCodec. Invoked in every generated glue::protocols module.
+#[macro_export]
+macro_rules! translated_codec {
+    ($codec:ident) => {
+        #[derive(Debug, Clone, Copy, Default)]
+        pub struct $codec;
+
+        impl $codec {
+            pub fn layout() -> &'static $crate::base_station_client::codec::WireLayout {
+                static LAYOUT: std::sync::OnceLock<$crate::base_station_client::codec::WireLayout> = std::sync::OnceLock::new();
+                LAYOUT.get_or_init(|| $crate::wire_layout!())
+            }
+        }
+
+        impl $crate::base_station_client::codec::ProtocolCodec for $codec {
+            fn protocol_version(&self) -> (u8, u8) {
+                (CONST_PROTOCOL_VERSION_MAJOR, CONST_PROTOCOL_VERSION_MINOR)
+            }
+
+            fn decode(&self, data: &[u8]) -> Option<Vec<u8>> {
+                $crate::base_station_client::codec::translate(data, Self::layout(), $crate::base_station_client::codec::CurrentCodec::layout())
+            }
+
+            fn encode(&self, data: &[u8]) -> Option<Vec<u8>> {
+                $crate::base_station_client::codec::translate(data, $crate::base_station_client::codec::CurrentCodec::layout(), Self::layout())
+            }
+        }
+    };
+}
+
+// Translate a frame from one layout into another. Messages are matched by the name of their
+// message type and only translated if their payload and every field in it have the same size and
+// offset in both versions. Enum values are matched by name, a message with a value the other
+// version doesn't have can't be translated, except for config variables, which become NONE.
+// Base_Information keeps just the version if its size changed.
+pub fn translate(data: &[u8], from: &WireLayout, to: &WireLayout) -> Option<Vec<u8>> {
+    if data.len() == from.base_information_len {
+        let mut out = vec![0; to.base_information_len];
+        if from.base_information_len == to.base_information_len {
+            out.copy_from_slice(data);
+        } else if from.version.1 == to.version.1 {
+            let (offset, len) = from.version;
+            out[to.version.0..to.version.0 + len].copy_from_slice(&data[offset..offset + len]);
+        } else {
+            return None;
+        }
+        return Some(out);
+    }
+
+    if data.len() != from.wrapper_len {
+        return None;
+    }
+    let mt = data[from.message_type_offset];
+    let &(name, _, len) = from.messages.iter().find(|m| m.1 == mt)?;
+    let &(_, to_mt, to_len) = to.messages.iter().find(|m| m.0 == name)?;
+    let fields = |layout: &WireLayout| layout.fields.iter().filter(|field| field.0 == name).copied().collect::<Vec<_>>();
+    if len != to_len || fields(from) != fields(to) {
+        return None;
+    }
+
+    let mut out = vec![0; to.wrapper_len];
+    out[to.id_offset] = data[from.id_offset];
+    out[to.message_type_offset] = to_mt;
+    out[to.payload_offset..to.payload_offset + len].copy_from_slice(&data[from.payload_offset..from.payload_offset + len]);
+
+    let variant_value = |layout: &WireLayout, kind: &str, variant: &str| layout.enums.iter().find(|v| v.0 == kind && v.1 == variant).map(|v| v.2);
+    for (_, _, offset, field_len, kind) in fields(from) {
+        let Some(kind) = kind else { continue; };
+        for i in offset..offset + field_len {
+            let value = data[from.payload_offset + i];
+            let value = from.enums.iter().find(|v| v.0 == kind && v.2 == value).and_then(|v| variant_value(to, kind, &v.1));
+            out[to.payload_offset + i] = match value {
+                Some(value) => value,
+                None if kind == "HG_Variable" => variant_value(to, kind, "NONE").unwrap_or(0),
+                None => return None,
+            };
+        }
+    }
+
+    if name == "PrimaryStatusHF" {
+        let mask = |states: &[(String, Vec<u8>)], i: usize| states.iter().fold(0, |mask, state| mask | state.1[i]);
+        let (from_offset, from_states) = &from.reflex_state;
+        let (to_offset, to_states) = &to.reflex_state;
+        let bits = &data[from.payload_offset + from_offset..][..from_states.first()?.1.len()];
+        let state = from_states.iter().find(|state| state.1.iter().enumerate().all(|(i, &b)| bits[i] & mask(from_states, i) == b))?;
+        let to_state = to_states.iter().find(|to_state| to_state.0 == state.0)?;
+        for (i, &b) in to_state.1.iter().enumerate() {
+            let byte = &mut out[to.payload_offset + to_offset + i];
+            *byte = (*byte & !mask(from_states, i) & !mask(to_states, i)) | b;
+        }
+    }
+    Some(out)
+}
+
+// The protocol version glue was generated from, frames pass through unchanged
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CurrentCodec;
+
+impl CurrentCodec {
+    pub fn layout() -> &'static WireLayout {
+        static LAYOUT: std::sync::OnceLock<WireLayout> = std::sync::OnceLock::new();
+        LAYOUT.get_or_init(|| crate::wire_layout!())
+    }
+}
+
+impl ProtocolCodec for CurrentCodec {
+    fn protocol_version(&self) -> (u8, u8) {
+        (CONST_PROTOCOL_VERSION_MAJOR, CONST_PROTOCOL_VERSION_MINOR)
+    }
+
+    fn decode(&self, data: &[u8]) -> Option<Vec<u8>> {
+        let layout = Self::layout();
+        (data.len() == layout.base_information_len || data.len() == layout.wrapper_len).then(|| data.to_vec())
+    }
+
+    fn encode(&self, data: &[u8]) -> Option<Vec<u8>> {
+        Some(data.to_vec())
+    }
+}
+
+// Every protocol version this build can talk to, the current one first
+pub fn codecs() -> Vec<Box<dyn ProtocolCodec>> {
+    let mut codecs: Vec<Box<dyn ProtocolCodec>> = vec![Box::new(CurrentCodec)];
+    codecs.extend(protocols::legacy_codecs());
+    codecs
+}
+
+pub fn codec_for(protocols_major: u8, protocols_minor: u8) -> Option<Box<dyn ProtocolCodec>> {
+    codecs().into_iter().find(|c| c.protocol_version() == (protocols_major, protocols_minor))
+}
+
+pub fn supported_protocol_versions() -> Vec<(u8, u8)> {
+    codecs().iter().map(|c| c.protocol_version()).collect()
+}
+
+// Find a Base_Information frame in a format other than the given codec's, to detect which
+// protocol version a base station is using. Returns the frame in the current layout.
+pub fn detect_base_information(current: &dyn ProtocolCodec, data: &[u8]) -> Option<(Box<dyn ProtocolCodec>, Vec<u8>)> {
+    codecs()
+        .into_iter()
+        .filter(|c| c.protocol_version() != current.protocol_version())
+        .find_map(|c| {
+            let decoded = c.decode(data).filter(|d| d.len() == CurrentCodec::layout().base_information_len)?;
+            Some((c, decoded))
+        })
+}
+
+#[cfg(test)]
+mod codec_tests {
+    use super::*;
+    use zerocopy::AsBytes;
+
+    // An older protocol version in which the message types, robot commands and config variables
+    // are numbered differently, the way build.rs generates it from a second checkout
+    #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+    mod v0_1 {
+        pub use crate::glue::*;
+
+        pub const CONST_PROTOCOL_VERSION_MAJOR: u8 = 0;
+        pub const CONST_PROTOCOL_VERSION_MINOR: u8 = 1;
+
+        #[repr(u8)]
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, num_derive::FromPrimitive)]
+        pub enum Radio_MessageType {
+            None = 0,
+            GlobalCommand,
+            Command,
+            MultiConfigMessage,
+            ImuReadings,
+            PrimaryStatusHF,
+            PrimaryStatusLF,
+            OdometryReading,
+            OverrideOdometry,
+            SerialMessage,
+        }
+
+        #[repr(u8)]
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, num_derive::FromPrimitive)]
+        pub enum Radio_RobotCommand {
+            NONE = 0,
+            CHIP = 1,
+            KICK = 2,
+        }
+
+        #[repr(u8)]
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, num_derive::FromPrimitive)]
+        pub enum HG_Variable {
+            NONE = 0,
+            KI = 1,
+            KP = 2,
+        }
+
+        crate::translated_codec!(Codec);
+    }
+
+    fn wrapper(id: Radio_SSL_ID, msg: Radio_Message_Rust) -> Vec<u8> {
+        Radio_MessageWrapper { id, _pad: [0, 0, 0], msg: msg.wrap() }.as_bytes().to_vec()
+    }
+
+    #[test]
+    fn current_codec_round_trip() {
+        let codec = CurrentCodec;
+        let imu = Radio_ImuReadings {
+            ang_wx: 1.0,
+            ang_wy: 2.0,
+            ang_wz: 3.0,
+            ang_x: 4.0,
+            ang_y: 5.0,
+            ang_z: 6.0,
+        };
+        let data = codec.encode(&wrapper(3, Radio_Message_Rust::ImuReadings(imu))).unwrap();
+        assert_eq!(codec.decode(&data), Some(data.clone()));
+        assert_eq!(codec.decode(&data[1..]), None);
+    }
+
+    #[test]
+    fn legacy_codec_round_trip() {
+        let codec = v0_1::Codec;
+        let legacy = v0_1::Codec::layout();
+        assert_eq!(codec.protocol_version(), (0, 1));
+
+        // The command is sent with the message type number of v0.1
+        let command = wrapper(2, Radio_Message_Rust::Command(crate::RobotCommand::new().velocity(1.0, 0.0, 0.5).to_command()));
+        let encoded = codec.encode(&command).unwrap();
+        assert_eq!(encoded[legacy.message_type_offset], v0_1::Radio_MessageType::Command as u8);
+        assert_ne!(encoded, command);
+        // The rest of the union isn't carried over
+        let len = CurrentCodec::layout().payload_offset + std::mem::size_of::<Radio_Command>();
+        assert_eq!(codec.decode(&encoded).unwrap()[..len], command[..len]);
+
+        // Config variables are renumbered, RADIO_CHANNEL doesn't exist in v0.1
+        let mcm = Radio_MultiConfigMessage::write().add(HG_Variable::KP, 5).add(HG_Variable::RADIO_CHANNEL, 7);
+        let encoded = codec.encode(&wrapper(Radio_BaseStation_ID, Radio_Message_Rust::MultiConfigMessage(mcm))).unwrap();
+        let vars = legacy.payload_offset + std::mem::offset_of!(Radio_MultiConfigMessage, vars);
+        assert_eq!(encoded[vars..vars + 2], [v0_1::HG_Variable::KP as u8, v0_1::HG_Variable::NONE as u8]);
+        let decoded = Radio_MessageWrapper::from_bytes(codec.decode(&encoded).unwrap()).unwrap();
+        match Radio_Message_Rust::unwrap(decoded.msg) {
+            Radio_Message_Rust::MultiConfigMessage(decoded) => {
+                assert_eq!(decoded.vars[..2], [HG_Variable::KP, HG_Variable::NONE]);
+                assert_eq!(decoded.values[..2], [5, 7]);
+            }
+            _ => panic!("config message did not round-trip"),
+        }
+
+        // A base station on v0.1 is recognised by its Base_Information
+        let base_info = Base_Information {
+            version: HG_Version { major: 1, minor: 2, patch: 3, protocols_major: 0, protocols_minor: 1 },
+            num_radios: 2,
+            max_robots: 16,
+            radios_online: 3,
+        };
+        let decoded = codec.decode(base_info.as_bytes()).unwrap();
+        assert_eq!(Base_Information::from_bytes(decoded).unwrap().version.protocols_minor, 1);
+    }
+
+    #[test]
+    fn renumbered_enums() {
+        let codec = v0_1::Codec;
+        let legacy = v0_1::Codec::layout();
+        let robot_command = legacy.payload_offset + std::mem::offset_of!(Radio_Command, gen_command.robot_command);
+
+        let mut command = crate::RobotCommand::new().to_command();
+        command.gen_command.robot_command = Radio_RobotCommand::KICK;
+        let encoded = codec.encode(&wrapper(2, Radio_Message_Rust::Command(command))).unwrap();
+        assert_eq!(encoded[robot_command], v0_1::Radio_RobotCommand::KICK as u8);
+        let decoded = Radio_MessageWrapper::from_bytes(codec.decode(&encoded).unwrap()).unwrap();
+        match Radio_Message_Rust::unwrap(decoded.msg) {
+            Radio_Message_Rust::Command(decoded) => assert_eq!(decoded.gen_command.robot_command, Radio_RobotCommand::KICK),
+            _ => panic!("command did not round-trip"),
+        }
+
+        // A value v0.1 doesn't have can't be sent
+        let mut unknown = encoded.clone();
+        unknown[robot_command] = 7;
+        assert_eq!(codec.decode(&unknown), None);
+    }
+
+    #[test]
+    fn moved_fields() {
+        // Same payload size, but two fields swapped places
+        let current = CurrentCodec::layout();
+        let mut moved = current.clone();
+        let offset = |layout: &WireLayout, field: &str| layout.fields.iter().position(|f| f.0 == "ImuReadings" && f.1 == field).unwrap();
+        let (x, z) = (offset(current, "ang_x"), offset(current, "ang_z"));
+        moved.fields[x].2 = current.fields[z].2;
+        moved.fields[z].2 = current.fields[x].2;
+
+        let imu = Radio_ImuReadings { ang_wx: 1.0, ang_wy: 2.0, ang_wz: 3.0, ang_x: 4.0, ang_y: 5.0, ang_z: 6.0 };
+        let data = wrapper(1, Radio_Message_Rust::ImuReadings(imu));
+        assert_eq!(translate(&data, current, &moved), None);
+        let len = current.payload_offset + std::mem::size_of::<Radio_ImuReadings>();
+        assert_eq!(translate(&data, current, current).unwrap()[..len], data[..len]);
+    }
+
+    #[test]
+    fn registry() {
+        let current = (CONST_PROTOCOL_VERSION_MAJOR, CONST_PROTOCOL_VERSION_MINOR);
+        assert_eq!(supported_protocol_versions()[0], current);
+        assert!(codec_for(current.0, current.1).is_some());
+    }
+}
@@ -0,0 +1,335 @@
+use super::field::{FieldFrame, RotationDirection};
+use super::scaling::{self, Encoded};
+use crate::glue::{HG_Pose, Radio_Command, Radio_GenericCommand, Radio_GlobalCommand, Radio_RobotCommand};
+
+// Builds Radio_Command and Radio_GlobalCommand from SI values. Scaled fields are rounded and
+// saturate at the limits of their integer type, padding is always zeroed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RobotCommand {
+    // [m/s]
+    pub speed_x: f32,
+    // [m/s]
+    pub speed_y: f32,
+    // [rad/s]
+    pub speed_w: f32,
+    // [rad/s]
+    pub dribbler_speed: f32,
+    // [ms]
+    pub kick_time: f32,
+    pub robot_command: Radio_RobotCommand,
+}
+
+impl Default for RobotCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RobotCommand {
+    // Standing still, dribbler off, no kick
+    pub fn new() -> Self {
+        RobotCommand {
+            speed_x: 0.0,
+            speed_y: 0.0,
+            speed_w: 0.0,
+            dribbler_speed: 0.0,
+            kick_time: 0.0,
+            robot_command: Radio_RobotCommand::NONE,
+        }
+    }
+
+    // Velocity in the robot frame, x and y in m/s, w in rad/s
+    pub fn velocity(mut self, x: f32, y: f32, w: f32) -> Self {
+        self.speed_x = x;
+        self.speed_y = y;
+        self.speed_w = w;
+        self
+    }
+
+    // Dribbler speed in rad/s
+    pub fn dribbler(mut self, speed: f32) -> Self {
+        self.dribbler_speed = speed;
+        self
+    }
+
+    // Kick time in ms
+    pub fn kick_time(mut self, ms: f32) -> Self {
+        self.kick_time = ms.max(0.0);
+        self
+    }
+
+    // Kick energy in Joules, converted to a kick time with the kicker's energy per ms
+    pub fn kick_energy(self, joules: f32, joules_per_ms: f32) -> Self {
+        if joules_per_ms <= 0.0 {
+            return self.kick_time(0.0);
+        }
+        self.kick_time(joules / joules_per_ms)
+    }
+
+    pub fn robot_command(mut self, robot_command: Radio_RobotCommand) -> Self {
+        self.robot_command = robot_command;
+        self
+    }
+
+    // Decode a command back into SI values
+    pub fn decode(command: &Radio_Command) -> Self {
+        RobotCommand {
+            speed_x: command.speed.x,
+            speed_y: command.speed.y,
+            speed_w: command.speed.z,
+            ..Self::decode_generic_command(&command.gen_command)
+        }
+    }
+
+    // Only the generic part, the velocity is zero
+    pub fn decode_generic_command(gen_command: &Radio_GenericCommand) -> Self {
+        RobotCommand {
+            dribbler_speed: scaling::DRIBBLER_SPEED.decode(gen_command.dribbler_speed_i),
+            kick_time: scaling::KICK_TIME.decode(gen_command.kick_time_i),
+            robot_command: gen_command.robot_command,
+            ..Self::new()
+        }
+    }
+
+    // The generic part of the command. saturated is set if the dribbler speed or kick time
+    // didn't fit and was clamped.
+    pub fn encode_generic_command(&self) -> Encoded<Radio_GenericCommand> {
+        let dribbler_speed = scaling::DRIBBLER_SPEED.encode(self.dribbler_speed);
+        let kick_time = scaling::KICK_TIME.encode(self.kick_time);
+        Encoded {
+            raw: Radio_GenericCommand {
+                dribbler_speed_i: dribbler_speed.raw,
+                kick_time_i: kick_time.raw,
+                time_to_kick: 0,
+                smart_kick_couter: 0,
+                robot_command: self.robot_command,
+            },
+            saturated: dribbler_speed.saturated || kick_time.saturated,
+        }
+    }
+
+    pub fn generic_command(&self) -> Radio_GenericCommand {
+        self.encode_generic_command().raw
+    }
+
+    // Whether any value had to be clamped to fit the command
+    pub fn is_saturated(&self) -> bool {
+        self.encode_generic_command().saturated
+    }
+
+    pub fn to_command(&self) -> Radio_Command {
+        Radio_Command {
+            speed: HG_Pose {
+                x: self.speed_x,
+                y: self.speed_y,
+                z: self.speed_w,
+            },
+            gen_command: self.generic_command(),
+            _pad: [0; 8],
+        }
+    }
+
+    // Global frame command. Only the x and y speed are used, the robot turns towards
+    // heading_setpoint by itself. Headings in rad.
+    pub fn to_global_command(&self, heading_last_measurement: f32, heading_setpoint: f32) -> Radio_GlobalCommand {
+        Radio_GlobalCommand {
+            global_speed_x: self.speed_x,
+            global_speed_y: self.speed_y,
+            heading_last_measurement,
+            heading_setpoint,
+            gen_command: self.generic_command(),
+            max_yaw_rate: 0,
+            preferred_rotation_direction: 0,
+            _pad: 0,
+        }
+    }
+}
+
+// Global frame command in the team frame, see FieldFrame. The robot drives with the field
+// velocity and turns towards heading by itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalCommand {
+    // [m/s]
+    pub speed_x: f32,
+    // [m/s]
+    pub speed_y: f32,
+    // [rad]
+    pub heading: f32,
+    // [rad/s], None leaves it to the robot
+    pub max_yaw_rate: Option<f32>,
+    pub rotation_direction: RotationDirection,
+    // Dribbler, kick and robot command, the velocity of it is ignored
+    pub generic: RobotCommand,
+}
+
+impl Default for GlobalCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GlobalCommand {
+    // Standing still facing the opponent goal, dribbler off, no kick
+    pub fn new() -> Self {
+        GlobalCommand {
+            speed_x: 0.0,
+            speed_y: 0.0,
+            heading: 0.0,
+            max_yaw_rate: None,
+            rotation_direction: RotationDirection::Shortest,
+            generic: RobotCommand::new(),
+        }
+    }
+
+    // Field velocity in m/s
+    pub fn velocity(mut self, x: f32, y: f32) -> Self {
+        self.speed_x = x;
+        self.speed_y = y;
+        self
+    }
+
+    // Heading setpoint in rad
+    pub fn heading(mut self, heading: f32) -> Self {
+        self.heading = heading;
+        self
+    }
+
+    // Turn no faster than rad_per_s towards the heading
+    pub fn max_yaw_rate(mut self, rad_per_s: f32) -> Self {
+        self.max_yaw_rate = Some(rad_per_s.max(0.0));
+        self
+    }
+
+    pub fn rotation_direction(mut self, direction: RotationDirection) -> Self {
+        self.rotation_direction = direction;
+        self
+    }
+
+    // Dribbler speed in rad/s
+    pub fn dribbler(mut self, speed: f32) -> Self {
+        self.generic = self.generic.dribbler(speed);
+        self
+    }
+
+    // Kick time in ms
+    pub fn kick_time(mut self, ms: f32) -> Self {
+        self.generic = self.generic.kick_time(ms);
+        self
+    }
+
+    pub fn robot_command(mut self, robot_command: Radio_RobotCommand) -> Self {
+        self.generic = self.generic.robot_command(robot_command);
+        self
+    }
+
+    // Decode a command in the vision frame, together with its heading_last_measurement
+    pub fn decode(gcommand: &Radio_GlobalCommand) -> (Self, f32) {
+        let command = GlobalCommand {
+            speed_x: gcommand.global_speed_x,
+            speed_y: gcommand.global_speed_y,
+            heading: gcommand.heading_setpoint,
+            max_yaw_rate: match gcommand.max_yaw_rate {
+                0 => None,
+                raw => Some(scaling::MAX_YAW_RATE.decode(raw)),
+            },
+            rotation_direction: RotationDirection::from_raw(gcommand.preferred_rotation_direction),
+            generic: RobotCommand::decode_generic_command(&gcommand.gen_command),
+        };
+        (command, gcommand.heading_last_measurement)
+    }
+
+    // heading_last_measurement is the latest measured heading of the robot in the team frame
+    pub fn encode(&self, frame: FieldFrame, heading_last_measurement: f32) -> Encoded<Radio_GlobalCommand> {
+        let (global_speed_x, global_speed_y) = frame.to_vision(self.speed_x, self.speed_y);
+        let generic = self.generic.encode_generic_command();
+        // 0 lets the robot use its own limit
+        let max_yaw_rate = match self.max_yaw_rate {
+            Some(rate) => scaling::MAX_YAW_RATE.encode::<u8>(rate),
+            None => Encoded { raw: 0, saturated: false },
+        };
+        Encoded {
+            raw: Radio_GlobalCommand {
+                global_speed_x,
+                global_speed_y,
+                heading_last_measurement: frame.heading_to_vision(heading_last_measurement),
+                heading_setpoint: frame.heading_to_vision(self.heading),
+                gen_command: generic.raw,
+                max_yaw_rate: max_yaw_rate.raw,
+                preferred_rotation_direction: self.rotation_direction.to_raw(),
+                _pad: 0,
+            },
+            saturated: generic.saturated || max_yaw_rate.saturated,
+        }
+    }
+
+    pub fn to_global_command(&self, frame: FieldFrame, heading_last_measurement: f32) -> Radio_GlobalCommand {
+        self.encode(frame, heading_last_measurement).raw
+    }
+}
+
+#[cfg(test)]
+mod command_tests {
+    use super::*;
+
+    #[test]
+    fn scales_and_zeroes_padding() {
+        let command = RobotCommand::new()
+            .velocity(1.0, -0.5, 2.0)
+            .dribbler(scaling::DRIBBLER_SPEED.decode(10))
+            .kick_time(scaling::KICK_TIME.decode(3))
+            .to_command();
+        assert_eq!(command.speed.x, 1.0);
+        assert_eq!(command.speed.y, -0.5);
+        assert_eq!(command.speed.z, 2.0);
+        assert_eq!(command.gen_command.dribbler_speed_i, 10);
+        assert_eq!(command.gen_command.kick_time_i, 3);
+        assert_eq!(command._pad, [0; 8]);
+        assert!(command.gen_command.robot_command == Radio_RobotCommand::NONE);
+    }
+
+    #[test]
+    fn saturates() {
+        let builder = RobotCommand::new()
+            .dribbler(f32::MAX)
+            .kick_time(-5.0);
+        assert!(builder.is_saturated());
+        assert!(!RobotCommand::new().dribbler(1.0).is_saturated());
+        let command = builder.generic_command();
+        assert!(command.dribbler_speed_i > 0);
+        assert_eq!(command.dribbler_speed_i, command.dribbler_speed_i.saturating_add(1));
+        assert_eq!(command.kick_time_i, 0);
+    }
+
+    #[test]
+    fn kick_energy() {
+        let command = RobotCommand::new().kick_energy(6.0, 2.0);
+        assert_eq!(command.kick_time, 3.0);
+        assert_eq!(RobotCommand::new().kick_energy(6.0, 0.0).kick_time, 0.0);
+    }
+
+    #[test]
+    fn global_command() {
+        use super::super::field::FieldSide;
+
+        let command = GlobalCommand::new()
+            .velocity(1.0, 0.5)
+            .heading(0.25)
+            .max_yaw_rate(scaling::MAX_YAW_RATE.decode(7))
+            .rotation_direction(RotationDirection::Clockwise)
+            .kick_time(scaling::KICK_TIME.decode(3));
+        let encoded = command.encode(FieldFrame::default(), 0.0);
+        assert!(!encoded.saturated);
+        let raw = encoded.raw;
+        assert_eq!((raw.global_speed_x, raw.global_speed_y), (1.0, 0.5));
+        assert_eq!(raw.heading_setpoint, 0.25);
+        assert_eq!(raw.max_yaw_rate, 7);
+        assert_eq!(raw.preferred_rotation_direction, -1);
+        assert_eq!(raw.gen_command.kick_time_i, 3);
+
+        let raw = command.to_global_command(FieldFrame::new(FieldSide::PositiveX), 0.0);
+        assert_eq!((raw.global_speed_x, raw.global_speed_y), (-1.0, -0.5));
+        assert!((raw.heading_setpoint - (0.25 - std::f32::consts::PI)).abs() < 1e-6);
+        assert!((raw.heading_last_measurement + std::f32::consts::PI).abs() < 1e-6);
+        assert_eq!(GlobalCommand::new().to_global_command(FieldFrame::default(), 0.0).max_yaw_rate, 0);
+    }
+}
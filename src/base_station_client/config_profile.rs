@@ -0,0 +1,248 @@
+use crate::glue::{HG_Variable, CONST_PROTOCOL_VERSION_MAJOR, CONST_PROTOCOL_VERSION_MINOR};
+use num_traits::ToPrimitive;
+use strum::IntoEnumIterator;
+use serde::{Deserialize, Serialize};
+
+// Bump this when the layout of the profile file changes
+pub const PROFILE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProfileError {
+    // Parsing a profile file failed, with the message of the TOML or JSON parser
+    Parse(String),
+    // The profile names a variable this protocol version doesn't have
+    UnknownVariable(String),
+    // The file was written by a newer version of glue
+    UnsupportedFormat(u32),
+    // The profile was read with protocol (major, minor), the robot uses the second one
+    ProtocolMismatch((u8, u8), (u8, u8)),
+    // The monitor could not queue a message for the base station
+    SendFailed,
+    // The robot did not return these variables in time
+    Timeout(Vec<HG_Variable>),
+    // Reading back after a write gave (variable, written, read back)
+    VerifyFailed(Vec<(HG_Variable, u32, u32)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProfileDifference {
+    Changed(HG_Variable, u32, u32),
+    OnlyInLeft(HG_Variable, u32),
+    OnlyInRight(HG_Variable, u32),
+}
+
+// A snapshot of all configuration variables of a single robot
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigProfile {
+    pub format_version: u32,
+    pub protocols_major: u8,
+    pub protocols_minor: u8,
+    pub robot_id: Option<u8>,
+    // Sorted by variable number, only contains profile variables
+    values: Vec<(HG_Variable, u32)>,
+}
+
+// The radio channel isn't part of a profile, writing it would move the robot away in the middle
+// of the transaction. Use Monitor::switch_channel instead.
+pub fn is_profile_variable(var: HG_Variable) -> bool {
+    !matches!(var, HG_Variable::NONE | HG_Variable::RADIO_CHANNEL)
+}
+
+// All variables that make up a full profile
+pub fn profile_variables() -> Vec<HG_Variable> {
+    HG_Variable::iter().filter(|&var| is_profile_variable(var)).collect()
+}
+
+fn variable_name(var: HG_Variable) -> String {
+    format!("{:?}", var)
+}
+
+fn variable_from_name(name: &str) -> Option<HG_Variable> {
+    HG_Variable::iter().find(|&var| variable_name(var) == name)
+}
+
+fn variable_number(var: HG_Variable) -> u8 {
+    var.to_u8().unwrap_or(0)
+}
+
+impl ConfigProfile {
+    pub fn new(robot_id: Option<u8>) -> Self {
+        ConfigProfile {
+            format_version: PROFILE_FORMAT_VERSION,
+            protocols_major: CONST_PROTOCOL_VERSION_MAJOR,
+            protocols_minor: CONST_PROTOCOL_VERSION_MINOR,
+            robot_id,
+            values: Vec::new(),
+        }
+    }
+
+    pub fn set(&mut self, var: HG_Variable, value: u32) {
+        if !is_profile_variable(var) { return; }
+        match self.values.binary_search_by_key(&variable_number(var), |(v, _)| variable_number(*v)) {
+            Ok(index) => self.values[index].1 = value,
+            Err(index) => self.values.insert(index, (var, value)),
+        }
+    }
+
+    pub fn get(&self, var: HG_Variable) -> Option<u32> {
+        self.values.iter().find(|(v, _)| *v == var).map(|(_, value)| *value)
+    }
+
+    pub fn values(&self) -> &[(HG_Variable, u32)] {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // Lists every variable that differs between self (left) and other (right)
+    pub fn diff(&self, other: &ConfigProfile) -> Vec<ProfileDifference> {
+        let mut diffs = Vec::new();
+        for &(var, value) in &self.values {
+            match other.get(var) {
+                Some(other_value) if other_value != value => diffs.push(ProfileDifference::Changed(var, value, other_value)),
+                Some(_) => (),
+                None => diffs.push(ProfileDifference::OnlyInLeft(var, value)),
+            }
+        }
+        for &(var, value) in &other.values {
+            if self.get(var).is_none() {
+                diffs.push(ProfileDifference::OnlyInRight(var, value));
+            }
+        }
+        diffs
+    }
+
+    pub fn protocol_version(&self) -> (u8, u8) {
+        (self.protocols_major, self.protocols_minor)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(&ProfileFile::from(self)).unwrap_or_default()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&ProfileFile::from(self)).unwrap_or_default()
+    }
+
+    // Unknown variables are rejected, as they most likely come from a different protocol version
+    pub fn from_toml(text: &str) -> Result<Self, ProfileError> {
+        toml::from_str::<ProfileFile>(text)
+            .map_err(|e| ProfileError::Parse(e.to_string()))?
+            .try_into()
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, ProfileError> {
+        serde_json::from_str::<ProfileFile>(text)
+            .map_err(|e| ProfileError::Parse(e.to_string()))?
+            .try_into()
+    }
+}
+
+// Layout of a profile file, the same for TOML and JSON
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileFile {
+    format_version: u32,
+    // "major.minor"
+    protocol_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    robot_id: Option<u8>,
+    #[serde(default)]
+    variables: std::collections::BTreeMap<String, u32>,
+}
+
+impl From<&ConfigProfile> for ProfileFile {
+    fn from(profile: &ConfigProfile) -> Self {
+        ProfileFile {
+            format_version: profile.format_version,
+            protocol_version: format!("{}.{}", profile.protocols_major, profile.protocols_minor),
+            robot_id: profile.robot_id,
+            variables: profile.values.iter().map(|&(var, value)| (variable_name(var), value)).collect(),
+        }
+    }
+}
+
+impl TryFrom<ProfileFile> for ConfigProfile {
+    type Error = ProfileError;
+
+    fn try_from(file: ProfileFile) -> Result<Self, ProfileError> {
+        if file.format_version > PROFILE_FORMAT_VERSION {
+            return Err(ProfileError::UnsupportedFormat(file.format_version));
+        }
+        let bad_version = || ProfileError::Parse(format!("Bad protocol_version {}", file.protocol_version));
+        let (major, minor) = file.protocol_version.split_once('.').ok_or_else(bad_version)?;
+        let mut profile = ConfigProfile::new(file.robot_id);
+        profile.format_version = file.format_version;
+        profile.protocols_major = major.parse().map_err(|_| bad_version())?;
+        profile.protocols_minor = minor.parse().map_err(|_| bad_version())?;
+        for (name, value) in file.variables {
+            let var = variable_from_name(&name)
+                .filter(|&var| var != HG_Variable::NONE)
+                .ok_or(ProfileError::UnknownVariable(name))?;
+            profile.set(var, value);
+        }
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod config_profile_tests {
+    use super::*;
+
+    fn sample_profile() -> ConfigProfile {
+        let mut profile = ConfigProfile::new(Some(3));
+        for (i, var) in profile_variables().into_iter().enumerate() {
+            profile.set(var, 1000 + i as u32);
+        }
+        profile
+    }
+
+    #[test]
+    fn toml_and_json_round_trip() {
+        let profile = sample_profile();
+        assert!(!profile.is_empty());
+        assert_eq!(ConfigProfile::from_toml(&profile.to_toml()), Ok(profile.clone()));
+        assert_eq!(ConfigProfile::from_json(&profile.to_json()), Ok(profile));
+    }
+
+    #[test]
+    fn rejects_unknown_variables_and_newer_formats() {
+        assert_eq!(
+            ConfigProfile::from_toml("format_version = 1\nprotocol_version = \"3.1\"\n[variables]\nNOT_A_VARIABLE = 3\n"),
+            Err(ProfileError::UnknownVariable("NOT_A_VARIABLE".to_owned()))
+        );
+        assert_eq!(
+            ConfigProfile::from_json(r#"{"format_version": 99, "protocol_version": "3.1"}"#),
+            Err(ProfileError::UnsupportedFormat(99))
+        );
+        assert!(matches!(ConfigProfile::from_toml("format_version = \"one\"\n"), Err(ProfileError::Parse(_))));
+    }
+
+    #[test]
+    fn radio_channel_is_not_part_of_a_profile() {
+        assert!(!profile_variables().contains(&HG_Variable::RADIO_CHANNEL));
+        let mut profile = ConfigProfile::new(None);
+        profile.set(HG_Variable::RADIO_CHANNEL, 5);
+        assert!(profile.is_empty());
+    }
+
+    #[test]
+    fn diff() {
+        let left = sample_profile();
+        let mut right = sample_profile();
+        let vars = profile_variables();
+        right.set(vars[0], 7);
+        assert_eq!(left.diff(&right), vec![ProfileDifference::Changed(vars[0], 1000, 7)]);
+        assert!(left.diff(&left).is_empty());
+        assert_eq!(
+            ConfigProfile::new(None).diff(&right).len(),
+            right.len()
+        );
+    }
+}
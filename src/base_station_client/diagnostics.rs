@@ -0,0 +1,247 @@
+use super::kinematics::Kinematics;
+use super::robot::Robot;
+use super::tracker::{LastSeen, RobotTracker};
+use crate::glue::Radio_SSL_ID;
+
+// Motors 0 to 3 drive the wheels, motor 4 isn't diagnosed
+pub const DIAGNOSED_MOTORS: usize = 4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MotorVerdict {
+    // Not enough samples with the wheel commanded to turn yet
+    #[default]
+    Unknown,
+    Ok,
+    // Not turning while drawing a lot of current
+    Stalled,
+    // Not turning and drawing no current
+    Dead,
+    // Reported speed stays near zero while the motor draws a normal current
+    EncoderFault,
+    // Turning much faster than the commanded velocity asks for
+    Slipping,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiagnosticsConfig {
+    pub kinematics: Kinematics,
+    // Samples older than this are not used
+    pub window: std::time::Duration,
+    // A status is only compared with a command echo at most this much older
+    pub max_command_age: std::time::Duration,
+    // Samples with the wheel commanded to turn needed for a verdict
+    pub min_samples: usize,
+    // Below this expected wheel speed a sample says nothing about the motor [rad/s]
+    pub min_wheel_speed: f32,
+    // Measured below this fraction of the expected speed counts as not turning
+    pub tracking: f32,
+    // Measured above 1 + slip times the expected speed counts as slipping
+    pub slip: f32,
+    // [A]
+    pub dead_current: f32,
+    // [A]
+    pub stall_current: f32,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        DiagnosticsConfig {
+            kinematics: Kinematics::default(),
+            window: std::time::Duration::from_millis(500),
+            max_command_age: std::time::Duration::from_millis(100),
+            min_samples: 5,
+            min_wheel_speed: 5.0,
+            tracking: 0.2,
+            slip: 0.5,
+            dead_current: 0.1,
+            stall_current: 2.0,
+        }
+    }
+}
+
+// Sent as an event when the verdict of a motor changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MotorVerdictEvent {
+    pub id: Radio_SSL_ID,
+    pub motor: u8,
+    pub from: MotorVerdict,
+    pub to: MotorVerdict,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    time: std::time::Instant,
+    // [rad/s]
+    expected: [f32; DIAGNOSED_MOTORS],
+    // [rad/s]
+    measured: [f32; 5],
+    // [A]
+    current: [f32; 5],
+}
+
+// Recent samples and verdicts of the motors of one robot
+#[derive(Debug, Clone, Default)]
+pub struct MotorDiagnostics {
+    samples: std::collections::VecDeque<Sample>,
+    verdicts: [MotorVerdict; 5],
+}
+
+impl MotorDiagnostics {
+    // Add a sample of expected and measured wheel speeds and currents, returns the new verdicts
+    pub fn add(
+        &mut self,
+        config: &DiagnosticsConfig,
+        time: std::time::Instant,
+        expected: [f32; DIAGNOSED_MOTORS],
+        measured: [f32; 5],
+        current: [f32; 5],
+    ) -> [MotorVerdict; 5] {
+        self.samples.push_back(Sample { time, expected, measured, current });
+        while self.samples.front().is_some_and(|sample| time.saturating_duration_since(sample.time) > config.window) {
+            self.samples.pop_front();
+        }
+        for motor in 0..DIAGNOSED_MOTORS {
+            if let Some(verdict) = self.evaluate(config, motor) {
+                self.verdicts[motor] = verdict;
+            }
+        }
+        self.verdicts
+    }
+
+    // None if there are too few samples with the wheel commanded to turn
+    fn evaluate(&self, config: &DiagnosticsConfig, motor: usize) -> Option<MotorVerdict> {
+        let samples: Vec<&Sample> = self.samples.iter()
+            .filter(|sample| sample.expected[motor].abs() >= config.min_wheel_speed)
+            .collect();
+        if samples.is_empty() || samples.len() < config.min_samples { return None; }
+
+        let n = samples.len() as f32;
+        let expected = samples.iter().map(|s| s.expected[motor].abs()).sum::<f32>() / n;
+        // Turning the wrong way counts against tracking
+        let measured = samples.iter().map(|s| s.measured[motor] * s.expected[motor].signum()).sum::<f32>() / n;
+        let current = samples.iter().map(|s| s.current[motor].abs()).sum::<f32>() / n;
+
+        let ratio = measured / expected;
+        Some(if ratio < config.tracking {
+            if current < config.dead_current { MotorVerdict::Dead }
+            else if current > config.stall_current { MotorVerdict::Stalled }
+            else { MotorVerdict::EncoderFault }
+        } else if ratio > 1.0 + config.slip {
+            MotorVerdict::Slipping
+        } else {
+            MotorVerdict::Ok
+        })
+    }
+
+    pub fn verdicts(&self) -> [MotorVerdict; 5] {
+        self.verdicts
+    }
+}
+
+// Motor diagnostics of a robot, kept by the monitor thread
+#[derive(Debug, Default)]
+pub(crate) struct DiagnosticsTracker {
+    diagnostics: MotorDiagnostics,
+    last_status: LastSeen,
+}
+
+impl RobotTracker for DiagnosticsTracker {
+    type Config = DiagnosticsConfig;
+    type Event = MotorVerdictEvent;
+
+    // Add a new PrimaryStatusHF, compared with the latest echoed command, and store the
+    // verdicts on the robot. Statuses are skipped while the robot follows global commands,
+    // whose body velocity depends on its heading controller, and
+    // when the latest echo is older than max_command_age.
+    fn update(&mut self, config: &DiagnosticsConfig, id: Radio_SSL_ID, robot: &mut Robot) -> Vec<MotorVerdictEvent> {
+        let (Some(measured), Some(current), Some(command), Some(commanded)) =
+            (robot.motor_speeds(), robot.motor_currents(), robot.command(), robot.command_timestamp()) else { return vec![]; };
+        let Some(received) = self.last_status.take_new(robot.status_hf_timestamp()) else { return vec![]; };
+        if robot.global_command_timestamp().is_some_and(|global| global > commanded) { return vec![]; }
+        if received.saturating_duration_since(commanded) > config.max_command_age { return vec![]; }
+
+        let expected = config.kinematics.wheel_speeds(command.speed.x, command.speed.y, command.speed.z);
+        let before = self.diagnostics.verdicts();
+        let after = self.diagnostics.add(config, received, expected, measured, current);
+        robot.set_motor_verdicts(after);
+        before.iter().zip(after.iter()).enumerate()
+            .filter(|(_, (from, to))| from != to)
+            .map(|(motor, (&from, &to))| MotorVerdictEvent { id, motor: motor as u8, from, to })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod diagnostics_tests {
+    use super::*;
+    use crate::base_station_client::basestation::MAX_NUM_ROBOTS;
+    use crate::base_station_client::tracker::PerRobot;
+
+    #[test]
+    fn verdicts() {
+        let config = DiagnosticsConfig::default();
+        let start = std::time::Instant::now();
+        let mut diagnostics = MotorDiagnostics::default();
+        let expected = [20.0, -20.0, 20.0, -20.0];
+        // Motor 0 follows, 1 is stalled, 2 is dead, 3 reports nothing with a normal current
+        let measured = [19.0, -1.0, 0.0, 0.0, 0.0];
+        let current = [1.0, 4.0, 0.0, 1.0, 0.0];
+
+        let mut verdicts = [MotorVerdict::Unknown; 5];
+        for i in 0..config.min_samples {
+            assert_eq!(verdicts[0], MotorVerdict::Unknown);
+            verdicts = diagnostics.add(&config, start + std::time::Duration::from_millis(10 * i as u64), expected, measured, current);
+        }
+        assert_eq!(verdicts, [MotorVerdict::Ok, MotorVerdict::Stalled, MotorVerdict::Dead, MotorVerdict::EncoderFault, MotorVerdict::Unknown]);
+
+        // Standing still keeps the verdicts
+        let later = start + std::time::Duration::from_secs(2);
+        assert_eq!(diagnostics.add(&config, later, [0.0; 4], [0.0; 5], [0.0; 5]), verdicts);
+
+        // Old samples leave the window
+        let mut diagnostics = MotorDiagnostics::default();
+        for i in 0..config.min_samples as u64 {
+            diagnostics.add(&config, start + std::time::Duration::from_millis(10 * i), expected, [40.0, -20.0, 20.0, -20.0, 0.0], current);
+        }
+        assert_eq!(diagnostics.verdicts()[0], MotorVerdict::Slipping);
+        for i in 0..config.min_samples as u64 {
+            diagnostics.add(&config, later + std::time::Duration::from_millis(10 * i), expected, [20.0, -20.0, 20.0, -20.0, 0.0], current);
+        }
+        assert_eq!(diagnostics.verdicts()[0], MotorVerdict::Ok);
+    }
+
+    #[test]
+    fn skipped_statuses() {
+        let config = DiagnosticsConfig { max_command_age: std::time::Duration::from_millis(5), ..Default::default() };
+        let mut diagnostics = PerRobot::<DiagnosticsTracker>::default();
+        let mut robots: [Robot; MAX_NUM_ROBOTS] = Default::default();
+        // Only the timestamps matter here
+        let status_hf: crate::glue::Radio_PrimaryStatusHF = unsafe { std::mem::zeroed() };
+        let samples = |diagnostics: &PerRobot<DiagnosticsTracker>| diagnostics.get(0).unwrap().diagnostics.samples.len();
+
+        robots[0].update_command(crate::RobotCommand::new().velocity(1.0, 0.0, 0.0).to_command());
+        robots[0].update_status_hf(status_hf);
+        diagnostics.update(&config, &mut robots);
+        assert_eq!(samples(&diagnostics), 1);
+        // The same status isn't added twice
+        diagnostics.update(&config, &mut robots);
+        assert_eq!(samples(&diagnostics), 1);
+
+        // A stale echo
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        robots[0].update_status_hf(status_hf);
+        diagnostics.update(&config, &mut robots);
+        assert_eq!(samples(&diagnostics), 1);
+
+        // Driven by global commands
+        robots[0].update_global_command(crate::GlobalCommand::new().to_global_command(Default::default(), 0.0));
+        robots[0].update_status_hf(status_hf);
+        diagnostics.update(&config, &mut robots);
+        assert_eq!(samples(&diagnostics), 1);
+
+        robots[0].update_command(crate::RobotCommand::new().to_command());
+        robots[0].update_status_hf(status_hf);
+        diagnostics.update(&config, &mut robots);
+        assert_eq!(samples(&diagnostics), 2);
+    }
+}
@@ -0,0 +1,30 @@
+use super::battery::BatteryWarningEvent;
+use super::diagnostics::MotorVerdictEvent;
+use super::health::HealthAlert;
+use super::imu::ImuEvent;
+use super::kicker::KickEvent;
+use super::limits::LimitViolation;
+use super::odometry::{OdometrySample, OverrideStatus};
+use super::possession::PossessionEvent;
+use super::presence::PresenceEvent;
+use super::reflex::ReflexKickEvent;
+
+// Number of events kept when the application doesn't poll them, older ones are dropped
+pub const EVENT_QUEUE_LEN: usize = 256;
+
+// Things that happened in glue which the application may want to react to, see
+// Monitor::poll_event
+#[derive(Debug, Clone)]
+pub enum Event {
+    LimitViolation(LimitViolation),
+    ReflexKick(ReflexKickEvent),
+    Odometry(OdometrySample),
+    OdometryOverride(OverrideStatus),
+    Health(HealthAlert),
+    Battery(BatteryWarningEvent),
+    Motor(MotorVerdictEvent),
+    Possession(PossessionEvent),
+    Kick(KickEvent),
+    Imu(ImuEvent),
+    Presence(PresenceEvent),
+}
@@ -0,0 +1,111 @@
+// The application works in a team frame where its own goal is always at negative x. The robots
+// work in the vision frame, which is rotated by 180 degrees when we defend the positive half.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FieldSide {
+    // Own goal at negative x, team frame and vision frame are the same
+    #[default]
+    NegativeX,
+    // Own goal at positive x
+    PositiveX,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FieldFrame {
+    pub side: FieldSide,
+}
+
+// Wrap an angle to [-pi, pi)
+pub fn normalize_angle(angle: f32) -> f32 {
+    (angle + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI
+}
+
+impl FieldFrame {
+    pub fn new(side: FieldSide) -> Self {
+        FieldFrame { side }
+    }
+
+    pub fn is_flipped(&self) -> bool {
+        self.side == FieldSide::PositiveX
+    }
+
+    // Vector (position or velocity) from the team frame to the vision frame
+    pub fn to_vision(&self, x: f32, y: f32) -> (f32, f32) {
+        if self.is_flipped() { (-x, -y) } else { (x, y) }
+    }
+
+    // Rotating by 180 degrees is its own inverse
+    pub fn from_vision(&self, x: f32, y: f32) -> (f32, f32) {
+        self.to_vision(x, y)
+    }
+
+    pub fn heading_to_vision(&self, heading: f32) -> f32 {
+        if self.is_flipped() {
+            normalize_angle(heading + std::f32::consts::PI)
+        } else {
+            normalize_angle(heading)
+        }
+    }
+
+    pub fn heading_from_vision(&self, heading: f32) -> f32 {
+        self.heading_to_vision(heading)
+    }
+}
+
+// Which way the robot turns to reach its heading setpoint. Rotation directions are the same in
+// both frames, a rotation by 180 degrees doesn't mirror anything.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RotationDirection {
+    // Whichever way is shorter
+    #[default]
+    Shortest,
+    // Negative angular velocity
+    Clockwise,
+    // Positive angular velocity
+    CounterClockwise,
+}
+
+impl RotationDirection {
+    // Value of Radio_GlobalCommand::preferred_rotation_direction
+    pub fn to_raw(self) -> i8 {
+        match self {
+            Self::Shortest => 0,
+            Self::Clockwise => -1,
+            Self::CounterClockwise => 1,
+        }
+    }
+
+    pub fn from_raw(raw: i8) -> Self {
+        match raw.signum() {
+            -1 => Self::Clockwise,
+            1 => Self::CounterClockwise,
+            _ => Self::Shortest,
+        }
+    }
+}
+
+#[cfg(test)]
+mod field_tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn flip() {
+        let frame = FieldFrame::new(FieldSide::NegativeX);
+        assert_eq!(frame.to_vision(1.0, -2.0), (1.0, -2.0));
+        assert_eq!(frame.heading_to_vision(0.5), 0.5);
+
+        let frame = FieldFrame::new(FieldSide::PositiveX);
+        assert_eq!(frame.to_vision(1.0, -2.0), (-1.0, 2.0));
+        assert!((frame.heading_to_vision(0.5) - (0.5 - PI)).abs() < 1e-6);
+        assert!((frame.heading_from_vision(frame.heading_to_vision(-2.0)) + 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn angles() {
+        assert!((normalize_angle(3.0 * PI / 2.0) + PI / 2.0).abs() < 1e-6);
+        assert!((normalize_angle(-7.0 * PI / 2.0) - PI / 2.0).abs() < 1e-5);
+        for dir in [RotationDirection::Shortest, RotationDirection::Clockwise, RotationDirection::CounterClockwise] {
+            assert_eq!(RotationDirection::from_raw(dir.to_raw()), dir);
+        }
+    }
+}
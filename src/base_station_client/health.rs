@@ -0,0 +1,302 @@
+use super::basestation::MAX_NUM_ROBOTS;
+use super::battery::BatteryEstimate;
+use super::robot::Robot;
+use super::state::RobotState;
+use super::tracker::PerRobot;
+use crate::glue::{HG_Status, Radio_SSL_ID};
+
+// How often the monitor thread evaluates the health of the robots
+pub const HEALTH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HealthLevel {
+    #[default]
+    Ok,
+    Degraded,
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Component {
+    Primary,
+    Kicker,
+    Imu,
+    Tof,
+    Motor(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HealthReason {
+    Status(Component, HG_Status),
+    // [deg C]
+    MotorTemperature { motor: u8, temperature: f32 },
+    // Open circuit voltage from the battery estimate [V]
+    PackVoltage { pack: u8, voltage: f32 },
+    // The robot was seen before but has not sent anything recently
+    Offline,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Health {
+    // The worst level of all reasons
+    pub level: HealthLevel,
+    pub reasons: Vec<(HealthLevel, HealthReason)>,
+}
+
+// What a reason is about, without the measured value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReasonKind {
+    Status(Component, HG_Status),
+    MotorTemperature(u8),
+    PackVoltage(u8),
+    Offline,
+}
+
+impl HealthReason {
+    fn kind(&self) -> ReasonKind {
+        match *self {
+            HealthReason::Status(component, status) => ReasonKind::Status(component, status),
+            HealthReason::MotorTemperature { motor, .. } => ReasonKind::MotorTemperature(motor),
+            HealthReason::PackVoltage { pack, .. } => ReasonKind::PackVoltage(pack),
+            HealthReason::Offline => ReasonKind::Offline,
+        }
+    }
+}
+
+impl Health {
+    // Same level and the same reasons at the same levels, whatever the measured values
+    fn same_as(&self, other: &Health) -> bool {
+        self.level == other.level
+            && self.reasons.len() == other.reasons.len()
+            && self.reasons.iter().zip(&other.reasons).all(|((a, a_reason), (b, b_reason))| a == b && a_reason.kind() == b_reason.kind())
+    }
+
+    fn add(&mut self, level: HealthLevel, reason: HealthReason) {
+        if level == HealthLevel::Ok { return; }
+        self.level = self.level.max(level);
+        self.reasons.push((level, reason));
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.level == HealthLevel::Ok
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Threshold {
+    pub degraded: f32,
+    pub critical: f32,
+}
+
+impl Threshold {
+    pub fn new(degraded: f32, critical: f32) -> Self {
+        Threshold { degraded, critical }
+    }
+
+    fn above(&self, value: f32) -> HealthLevel {
+        if value > self.critical { HealthLevel::Critical }
+        else if value > self.degraded { HealthLevel::Degraded }
+        else { HealthLevel::Ok }
+    }
+
+    fn below(&self, value: f32) -> HealthLevel {
+        if value < self.critical { HealthLevel::Critical }
+        else if value < self.degraded { HealthLevel::Degraded }
+        else { HealthLevel::Ok }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthRules {
+    // Level of each reported HG_Status, statuses not listed are ok
+    pub statuses: Vec<(HG_Status, HealthLevel)>,
+    // Bad above the thresholds [deg C]
+    pub motor_temperature: Option<Threshold>,
+    // Bad below the thresholds, judged on the open circuit voltage of the battery estimate
+    // rather than the voltage under load [V]
+    pub pack_voltage: Option<Threshold>,
+    pub offline: HealthLevel,
+}
+
+impl Default for HealthRules {
+    fn default() -> Self {
+        HealthRules {
+            statuses: vec![
+                (HG_Status::OVERTEMP, HealthLevel::Degraded),
+                (HG_Status::NO_REPLY, HealthLevel::Critical),
+                (HG_Status::EMERGENCY, HealthLevel::Critical),
+            ],
+            motor_temperature: Some(Threshold::new(70.0, 80.0)),
+            // 4S LiPo, 3.5 V and 3.3 V per cell
+            pack_voltage: Some(Threshold::new(14.0, 13.2)),
+            offline: HealthLevel::Critical,
+        }
+    }
+}
+
+impl HealthRules {
+    pub fn status_level(&self, status: HG_Status) -> HealthLevel {
+        self.statuses.iter()
+            .find(|(s, _)| *s == status)
+            .map_or(HealthLevel::Ok, |(_, level)| *level)
+    }
+
+    // battery is the estimate of the robot's battery model, the pack voltage rule is skipped
+    // without one
+    pub fn evaluate(&self, state: &RobotState, battery: Option<&BatteryEstimate>) -> Health {
+        let mut health = Health::default();
+        let seen = state.status_hf.is_some() || state.status_lf.is_some() || state.imu.is_some() || state.odometry.is_some();
+        if seen && !state.online {
+            health.add(self.offline, HealthReason::Offline);
+        }
+
+        if let Some(status_lf) = state.status_lf {
+            let components = [
+                (Component::Primary, status_lf.primary_status),
+                (Component::Kicker, status_lf.kicker_status),
+                (Component::Imu, status_lf.imu_status),
+                (Component::Tof, status_lf.tof_status),
+            ];
+            let motors = status_lf.motor_statuses.iter().enumerate().map(|(i, &status)| (Component::Motor(i as u8), status));
+            for (component, status) in components.into_iter().chain(motors) {
+                health.add(self.status_level(status), HealthReason::Status(component, status));
+            }
+
+            if let Some(threshold) = self.motor_temperature {
+                for (motor, &temperature) in status_lf.motor_temperatures.iter().enumerate() {
+                    health.add(threshold.above(temperature), HealthReason::MotorTemperature { motor: motor as u8, temperature });
+                }
+            }
+        }
+        if let (Some(threshold), Some(battery)) = (self.pack_voltage, battery) {
+            for (pack, &voltage) in battery.open_circuit_voltages.iter().enumerate() {
+                health.add(threshold.below(voltage), HealthReason::PackVoltage { pack: pack as u8, voltage });
+            }
+        }
+        health
+    }
+}
+
+// Sent as an event when the health level of a robot or the reasons for it change
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthAlert {
+    pub id: Radio_SSL_ID,
+    pub from: HealthLevel,
+    pub to: HealthLevel,
+    pub health: Health,
+}
+
+// Latest health of every robot, shared between the Monitor and its thread
+#[derive(Debug, Default)]
+pub(crate) struct HealthTracker {
+    health: PerRobot<Health>,
+    last_update: Option<std::time::Instant>,
+}
+
+impl HealthTracker {
+    // Evaluate every robot if HEALTH_INTERVAL passed since the last time, batteries being the
+    // battery estimates by robot id
+    pub fn update(&mut self, rules: &HealthRules, robots: &[Robot; MAX_NUM_ROBOTS], batteries: &[Option<BatteryEstimate>; MAX_NUM_ROBOTS], now: std::time::Instant) -> Vec<HealthAlert> {
+        if self.last_update.is_some_and(|last| now.saturating_duration_since(last) < HEALTH_INTERVAL) { return vec![]; }
+        self.last_update = Some(now);
+        let mut alerts = vec![];
+        for ((id, latest), robot) in self.health.iter_mut().zip(robots.iter()) {
+            let health = rules.evaluate(&robot.state(), batteries[id as usize].as_ref());
+            if !health.same_as(latest) {
+                alerts.push(HealthAlert { id, from: latest.level, to: health.level, health: health.clone() });
+            }
+            *latest = health;
+        }
+        alerts
+    }
+
+    pub fn health(&self, id: Radio_SSL_ID) -> Option<Health> {
+        self.health.get(id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod health_tests {
+    use super::*;
+    use crate::base_station_client::state::StatusLF;
+
+    fn battery(open_circuit_voltages: [f32; 2]) -> BatteryEstimate {
+        BatteryEstimate { open_circuit_voltages, ..Default::default() }
+    }
+
+    fn status_lf() -> StatusLF {
+        StatusLF {
+            age: std::time::Duration::ZERO,
+            pack_voltages: [16.0, 16.0],
+            motor_temperatures: [40.0; 5],
+            cap_voltage: 0.0,
+            main_board_current: 1.0,
+            avg_loop_time: 100.0,
+            max_loop_time: 200.0,
+            avg_command_time: 10.0,
+            primary_status: HG_Status::OK,
+            kicker_status: HG_Status::ARMED,
+            tof_status: HG_Status::NOT_INSTALLED,
+            imu_status: HG_Status::OK,
+            motor_statuses: [HG_Status::OK; 5],
+        }
+    }
+
+    #[test]
+    fn evaluate() {
+        let rules = HealthRules::default();
+        assert!(rules.evaluate(&RobotState::default(), None).is_ok());
+
+        let mut state = RobotState { online: true, status_lf: Some(status_lf()), ..Default::default() };
+        assert!(rules.evaluate(&state, None).is_ok());
+
+        // A pack sagging under load is fine as long as the estimate isn't low
+        let mut lf = status_lf();
+        lf.motor_temperatures[2] = 75.0;
+        lf.pack_voltages[1] = 12.0;
+        lf.motor_statuses[4] = HG_Status::OVERTEMP;
+        state.status_lf = Some(lf);
+        assert_eq!(rules.evaluate(&state, Some(&battery([16.0, 15.5]))).level, HealthLevel::Degraded);
+        let health = rules.evaluate(&state, Some(&battery([16.0, 13.0])));
+        assert_eq!(health.level, HealthLevel::Critical);
+        assert_eq!(health.reasons, vec![
+            (HealthLevel::Degraded, HealthReason::Status(Component::Motor(4), HG_Status::OVERTEMP)),
+            (HealthLevel::Degraded, HealthReason::MotorTemperature { motor: 2, temperature: 75.0 }),
+            (HealthLevel::Critical, HealthReason::PackVoltage { pack: 1, voltage: 13.0 }),
+        ]);
+
+        state.online = false;
+        state.status_lf = Some(status_lf());
+        assert_eq!(rules.evaluate(&state, None).reasons, vec![(HealthLevel::Critical, HealthReason::Offline)]);
+
+        let relaxed = HealthRules { statuses: vec![], offline: HealthLevel::Degraded, ..Default::default() };
+        assert_eq!(relaxed.evaluate(&state, None).level, HealthLevel::Degraded);
+    }
+
+    #[test]
+    fn alerts() {
+        let rules = HealthRules::default();
+        let mut tracker = HealthTracker::default();
+        let robots: [Robot; MAX_NUM_ROBOTS] = Default::default();
+        let start = std::time::Instant::now();
+        let at = |ms| start + std::time::Duration::from_millis(ms);
+        let mut batteries = [None; MAX_NUM_ROBOTS];
+        assert!(tracker.update(&rules, &robots, &batteries, at(0)).is_empty());
+
+        // Degraded by the first pack, then also by the second: same level, new reason
+        batteries[3] = Some(battery([13.8, 16.0]));
+        let alerts = tracker.update(&rules, &robots, &batteries, at(100));
+        assert_eq!((alerts[0].id, alerts[0].from, alerts[0].to), (3, HealthLevel::Ok, HealthLevel::Degraded));
+        batteries[3] = Some(battery([13.7, 16.0]));
+        assert!(tracker.update(&rules, &robots, &batteries, at(200)).is_empty());
+        batteries[3] = Some(battery([13.7, 13.9]));
+        let alerts = tracker.update(&rules, &robots, &batteries, at(300));
+        assert_eq!((alerts[0].from, alerts[0].to), (HealthLevel::Degraded, HealthLevel::Degraded));
+        assert_eq!(alerts[0].health.reasons.len(), 2);
+
+        // Not evaluated again within HEALTH_INTERVAL
+        batteries[3] = None;
+        assert!(tracker.update(&rules, &robots, &batteries, at(350)).is_empty());
+        assert_eq!(tracker.update(&rules, &robots, &batteries, at(400))[0].to, HealthLevel::Ok);
+    }
+}
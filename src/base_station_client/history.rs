@@ -0,0 +1,277 @@
+use super::command::{GlobalCommand, RobotCommand};
+use super::imu::ImuSample;
+use super::odometry::{Odometry, OdometrySample};
+use super::state::*;
+use super::tracker::PerRobot;
+use crate::glue::{Radio_Command, Radio_GlobalCommand, Radio_PrimaryStatusHF, Radio_PrimaryStatusLF, Radio_SSL_ID};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HistoryConfig {
+    // Samples kept per robot and kind of message
+    pub len: usize,
+    // Samples older than this are dropped, None keeps them until len is reached
+    pub max_age: Option<std::time::Duration>,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            len: 2000,
+            max_age: None,
+        }
+    }
+}
+
+// Timestamped samples of one kind of message, oldest first
+#[derive(Debug, Clone)]
+pub struct Series<T> {
+    samples: std::collections::VecDeque<(std::time::Instant, T)>,
+}
+
+impl<T> Default for Series<T> {
+    fn default() -> Self {
+        Series { samples: std::collections::VecDeque::new() }
+    }
+}
+
+impl<T: Copy> Series<T> {
+    fn push(&mut self, config: &HistoryConfig, time: std::time::Instant, value: T) {
+        self.samples.push_back((time, value));
+        while self.samples.len() > config.len {
+            self.samples.pop_front();
+        }
+        if let Some(max_age) = config.max_age {
+            while self.samples.front().is_some_and(|(oldest, _)| time.saturating_duration_since(*oldest) > max_age) {
+                self.samples.pop_front();
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn latest(&self) -> Option<(std::time::Instant, T)> {
+        self.samples.back().copied()
+    }
+
+    // The last n samples, oldest first
+    pub fn last(&self, n: usize) -> Vec<(std::time::Instant, T)> {
+        self.samples.iter().skip(self.samples.len().saturating_sub(n)).copied().collect()
+    }
+
+    // Samples received after since, all of them if None
+    pub fn since(&self, since: Option<std::time::Instant>) -> Vec<(std::time::Instant, T)> {
+        let start = since.map_or(0, |since| self.samples.partition_point(|(time, _)| *time <= since));
+        self.samples.range(start..).copied().collect()
+    }
+
+    // Samples from from up to and including to
+    pub fn range(&self, from: std::time::Instant, to: std::time::Instant) -> Vec<(std::time::Instant, T)> {
+        self.values(from, to, |sample| *sample)
+    }
+
+    // One field of the samples from from up to and including to, e.g. |status| status.motor_speeds[0]
+    pub fn values<V>(
+        &self,
+        from: std::time::Instant,
+        to: std::time::Instant,
+        field: impl Fn(&T) -> V,
+    ) -> Vec<(std::time::Instant, V)> {
+        let start = self.samples.partition_point(|(time, _)| *time < from);
+        self.samples.range(start..)
+            .take_while(|(time, _)| *time <= to)
+            .map(|(time, sample)| (*time, field(sample)))
+            .collect()
+    }
+
+    // The first sample of every interval from from up to and including to
+    pub fn downsample(
+        &self,
+        from: std::time::Instant,
+        to: std::time::Instant,
+        interval: std::time::Duration,
+    ) -> Vec<(std::time::Instant, T)> {
+        // Index of the interval the last sample was taken from
+        let mut last_bucket = None;
+        let mut samples = vec![];
+        for (time, sample) in self.range(from, to) {
+            let bucket = time.saturating_duration_since(from).as_nanos() / interval.as_nanos().max(1);
+            if last_bucket != Some(bucket) {
+                last_bucket = Some(bucket);
+                samples.push((time, sample));
+            }
+        }
+        samples
+    }
+}
+
+// Everything received from one robot, decoded. The samples are the RobotState groups with an age
+// of zero, the time they were received is kept alongside.
+#[derive(Debug, Clone, Default)]
+pub struct RobotHistory {
+    pub status_hf: Series<StatusHF>,
+    pub status_lf: Series<StatusLF>,
+    pub imu: Series<Imu>,
+    pub odometry: Series<OdometryState>,
+    // Echoed commands
+    pub command: Series<CommandState>,
+    pub global_command: Series<GlobalCommandState>,
+}
+
+// A decoded status or echoed command, with an age of zero, see BaseStation::history_samples
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HistorySample {
+    StatusHF(StatusHF),
+    StatusLF(StatusLF),
+    Command(CommandState),
+    GlobalCommand(GlobalCommandState),
+}
+
+impl HistorySample {
+    pub fn of_status_hf(status_hf: &Radio_PrimaryStatusHF) -> Self {
+        HistorySample::StatusHF(StatusHF::decode(std::time::Duration::ZERO, status_hf))
+    }
+
+    pub fn of_status_lf(status_lf: &Radio_PrimaryStatusLF) -> Self {
+        HistorySample::StatusLF(StatusLF::decode(std::time::Duration::ZERO, status_lf))
+    }
+
+    pub fn of_command(command: &Radio_Command) -> Self {
+        HistorySample::Command(CommandState { age: std::time::Duration::ZERO, command: RobotCommand::decode(command) })
+    }
+
+    pub fn of_global_command(gcommand: &Radio_GlobalCommand) -> Self {
+        let (command, heading_last_measurement) = GlobalCommand::decode(gcommand);
+        HistorySample::GlobalCommand(GlobalCommandState { age: std::time::Duration::ZERO, command, heading_last_measurement })
+    }
+}
+
+// History of every robot, shared between the Monitor and its thread
+#[derive(Debug, Default)]
+pub(crate) struct History {
+    robots: PerRobot<RobotHistory>,
+}
+
+impl History {
+    pub fn record(&mut self, config: &HistoryConfig, id: Radio_SSL_ID, time: std::time::Instant, sample: HistorySample) {
+        let Some(history) = self.robots.get_mut(id) else { return; };
+        match sample {
+            HistorySample::StatusHF(status_hf) => history.status_hf.push(config, time, status_hf),
+            HistorySample::StatusLF(status_lf) => history.status_lf.push(config, time, status_lf),
+            HistorySample::Command(command) => history.command.push(config, time, command),
+            HistorySample::GlobalCommand(global_command) => history.global_command.push(config, time, global_command),
+        }
+    }
+
+    pub fn record_imu(&mut self, config: &HistoryConfig, sample: &ImuSample) {
+        let Some(history) = self.robots.get_mut(sample.id) else { return; };
+        let imu = Imu { age: std::time::Duration::ZERO, angular_velocity: sample.angular_velocity, angles: sample.angles };
+        history.imu.push(config, sample.timestamp, imu);
+    }
+
+    pub fn record_odometry(&mut self, config: &HistoryConfig, sample: &OdometrySample) {
+        let Some(history) = self.robots.get_mut(sample.id) else { return; };
+        history.odometry.push(config, sample.timestamp, OdometryState { age: std::time::Duration::ZERO, odometry: sample.odometry });
+    }
+
+    pub fn robot(&self, id: Radio_SSL_ID) -> Option<&RobotHistory> {
+        self.robots.get(id)
+    }
+
+    // Odometry readings of a robot received after since, all that are kept if None
+    pub fn odometry(&self, id: Radio_SSL_ID, since: Option<std::time::Instant>) -> Vec<(std::time::Instant, Odometry)> {
+        let Some(history) = self.robot(id) else { return vec![]; };
+        history.odometry.since(since).into_iter().map(|(time, state)| (time, state.odometry)).collect()
+    }
+
+    // IMU readings of a robot received after since, all that are kept if None
+    pub fn imu(&self, id: Radio_SSL_ID, since: Option<std::time::Instant>) -> Vec<ImuSample> {
+        let Some(history) = self.robot(id) else { return vec![]; };
+        history.imu.since(since).into_iter()
+            .map(|(timestamp, imu)| ImuSample { id, timestamp, angular_velocity: imu.angular_velocity, angles: imu.angles })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod history_tests {
+    use super::*;
+    use crate::base_station_client::basestation::MAX_NUM_ROBOTS;
+
+    #[test]
+    fn queries() {
+        let config = HistoryConfig { len: 5, max_age: None };
+        let start = std::time::Instant::now();
+        let ms = |ms| start + std::time::Duration::from_millis(ms);
+        let mut series = Series::<f32>::default();
+        for i in 0..8 {
+            series.push(&config, ms(10 * i), i as f32);
+        }
+
+        // Only the last 5 are kept
+        assert_eq!(series.len(), 5);
+        assert_eq!(series.latest(), Some((ms(70), 7.0)));
+        assert_eq!(series.last(2), vec![(ms(60), 6.0), (ms(70), 7.0)]);
+        assert_eq!(series.last(10).len(), 5);
+        assert_eq!(series.range(ms(35), ms(50)), vec![(ms(40), 4.0), (ms(50), 5.0)]);
+        assert_eq!(series.values(ms(0), ms(40), |value| value * 2.0), vec![(ms(30), 6.0), (ms(40), 8.0)]);
+        assert_eq!(series.downsample(ms(30), ms(70), std::time::Duration::from_millis(25)),
+            vec![(ms(30), 3.0), (ms(60), 6.0)]);
+        assert_eq!(series.since(Some(ms(50))), vec![(ms(60), 6.0), (ms(70), 7.0)]);
+        assert_eq!(series.since(None).len(), 5);
+
+        let config = HistoryConfig { len: 100, max_age: Some(std::time::Duration::from_millis(20)) };
+        series.push(&config, ms(80), 8.0);
+        assert_eq!(series.range(ms(0), ms(100)), vec![(ms(60), 6.0), (ms(70), 7.0), (ms(80), 8.0)]);
+    }
+
+    #[test]
+    fn sparse_downsample() {
+        let config = HistoryConfig::default();
+        let start = std::time::Instant::now();
+        let mut series = Series::<u8>::default();
+        let hours = |h: u64| start + std::time::Duration::from_secs(3600 * h);
+        series.push(&config, start, 0);
+        series.push(&config, hours(1), 1);
+        series.push(&config, hours(1), 2);
+        series.push(&config, hours(9), 3);
+
+        // Skipping the empty nanosecond buckets doesn't step through them
+        assert_eq!(series.downsample(start, hours(10), std::time::Duration::from_nanos(1)),
+            vec![(start, 0), (hours(1), 1), (hours(9), 3)]);
+        assert_eq!(series.downsample(start, hours(10), std::time::Duration::ZERO).len(), 3);
+    }
+
+    #[test]
+    fn record() {
+        let start = std::time::Instant::now();
+        let ms = |ms| start + std::time::Duration::from_millis(ms);
+        let config = HistoryConfig { len: 3, max_age: None };
+        let mut history = History::default();
+
+        // Every frame is kept, also several of the same kind between two updates of the monitor
+        for i in 0..5 {
+            let odometry = Odometry { x: i as f32, y: 0.5, heading: -1.0 };
+            history.record_odometry(&config, &OdometrySample { id: 2, timestamp: ms(i), odometry });
+            history.record_imu(&config, &ImuSample { id: 2, timestamp: ms(i), angular_velocity: [0.0, 0.0, i as f32], angles: [0.0; 3] });
+            let command = CommandState { age: std::time::Duration::ZERO, command: Default::default() };
+            history.record(&config, 2, ms(i), HistorySample::Command(command));
+        }
+        history.record_odometry(&config, &OdometrySample { id: MAX_NUM_ROBOTS as u8, timestamp: start, odometry: Odometry::default() });
+        history.record(&config, MAX_NUM_ROBOTS as u8, start, HistorySample::Command(CommandState { age: std::time::Duration::ZERO, command: Default::default() }));
+
+        assert_eq!(history.robot(2).unwrap().command.len(), 3);
+        assert_eq!(history.odometry(2, None).iter().map(|(_, odometry)| odometry.x).collect::<Vec<_>>(), vec![2.0, 3.0, 4.0]);
+        assert_eq!(history.odometry(2, Some(ms(3))), vec![(ms(4), Odometry { x: 4.0, y: 0.5, heading: -1.0 })]);
+        assert!(history.odometry(0, None).is_empty());
+        let imu = history.imu(2, Some(ms(2)));
+        assert_eq!(imu.len(), 2);
+        assert_eq!((imu[1].id, imu[1].timestamp, imu[1].angular_velocity[2]), (2, ms(4), 4.0));
+        assert!(history.robot(MAX_NUM_ROBOTS as u8).is_none());
+    }
+}
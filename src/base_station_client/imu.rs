@@ -0,0 +1,245 @@
+use super::basestation::MAX_NUM_ROBOTS;
+use super::field::normalize_angle;
+use super::robot::Robot;
+use super::tracker::PerRobot;
+use crate::glue::{Radio_ImuReadings, Radio_SSL_ID};
+
+// An IMU reading as received from a robot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImuSample {
+    pub id: Radio_SSL_ID,
+    pub timestamp: std::time::Instant,
+    // (x, y, z) [rad/s]
+    pub angular_velocity: [f32; 3],
+    // (x, y, z) [rad]
+    pub angles: [f32; 3],
+}
+
+impl ImuSample {
+    pub fn new(id: Radio_SSL_ID, timestamp: std::time::Instant, reading: &Radio_ImuReadings) -> Self {
+        ImuSample {
+            id,
+            timestamp,
+            angular_velocity: [reading.ang_wx, reading.ang_wy, reading.ang_wz],
+            angles: [reading.ang_x, reading.ang_y, reading.ang_z],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImuConfig {
+    // Below this yaw rate the robot is taken to stand still, and the gyro reading to be bias [rad/s]
+    pub still_rate: f32,
+    // Time constant of the low pass filter on the gyro bias
+    pub bias_time_constant: std::time::Duration,
+    // Roll or pitch beyond this is a tilt [rad]
+    pub tilt_angle: f32,
+    // Roll or pitch changing faster than this is a collision [rad/s]
+    pub collision_rate: f32,
+    // Collisions closer together than this are reported once
+    pub collision_holdoff: std::time::Duration,
+}
+
+impl Default for ImuConfig {
+    fn default() -> Self {
+        ImuConfig {
+            still_rate: 0.05,
+            bias_time_constant: std::time::Duration::from_secs(10),
+            tilt_angle: 0.15,
+            collision_rate: 3.0,
+            collision_holdoff: std::time::Duration::from_millis(250),
+        }
+    }
+}
+
+// Orientation of a robot estimated from its IMU readings
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Orientation {
+    // Integrated from the bias corrected yaw rate [rad]
+    pub heading: f32,
+    // Estimated yaw rate bias of the gyro [rad/s]
+    pub bias: f32,
+    // Integrated heading minus the heading reported by the IMU [rad]
+    pub drift: f32,
+    // [rad]
+    pub roll: f32,
+    // [rad]
+    pub pitch: f32,
+    pub tilted: bool,
+    pub last_collision: Option<std::time::Instant>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImuEventKind {
+    // [rad/s]
+    Collision { rate: f32 },
+    // [rad]
+    TiltStarted { roll: f32, pitch: f32 },
+    TiltEnded,
+}
+
+// Sent as an event when a robot collides, tilts or stands upright again
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImuEvent {
+    pub id: Radio_SSL_ID,
+    pub timestamp: std::time::Instant,
+    pub kind: ImuEventKind,
+}
+
+// Gyro integration, bias estimation and collision and tilt detection of one robot
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ImuEstimator {
+    orientation: Orientation,
+    last: Option<ImuSample>,
+}
+
+impl ImuEstimator {
+    // Add a reading, returns what happened
+    pub fn update(&mut self, config: &ImuConfig, sample: &ImuSample) -> Vec<ImuEventKind> {
+        let mut events = vec![];
+        let [roll, pitch, yaw] = sample.angles;
+        let orientation = &mut self.orientation;
+
+        match self.last {
+            Some(last) => {
+                let dt = sample.timestamp.saturating_duration_since(last.timestamp).as_secs_f32();
+                if dt > 0.0 {
+                    let yaw_rate = sample.angular_velocity[2];
+                    if yaw_rate.abs() < config.still_rate {
+                        let tau = config.bias_time_constant.as_secs_f32();
+                        let alpha = if tau > 0.0 { 1.0 - (-dt / tau).exp() } else { 1.0 };
+                        orientation.bias += alpha * (yaw_rate - orientation.bias);
+                    }
+                    orientation.heading = normalize_angle(orientation.heading + (yaw_rate - orientation.bias) * dt);
+
+                    let rate = normalize_angle(roll - last.angles[0]).abs().max(normalize_angle(pitch - last.angles[1]).abs()) / dt;
+                    let recent = orientation.last_collision
+                        .is_some_and(|collision| sample.timestamp.saturating_duration_since(collision) < config.collision_holdoff);
+                    if rate > config.collision_rate && !recent {
+                        orientation.last_collision = Some(sample.timestamp);
+                        events.push(ImuEventKind::Collision { rate });
+                    }
+                }
+            }
+            None => orientation.heading = yaw,
+        }
+        self.last = Some(*sample);
+
+        orientation.drift = normalize_angle(orientation.heading - yaw);
+        orientation.roll = roll;
+        orientation.pitch = pitch;
+        let tilted = roll.abs() > config.tilt_angle || pitch.abs() > config.tilt_angle;
+        if tilted && !orientation.tilted {
+            events.push(ImuEventKind::TiltStarted { roll, pitch });
+        } else if !tilted && orientation.tilted {
+            events.push(ImuEventKind::TiltEnded);
+        }
+        orientation.tilted = tilted;
+        events
+    }
+
+    // Reset the integrated heading, e.g. to the heading seen by vision [rad]
+    pub fn set_heading(&mut self, heading: f32) {
+        self.orientation.heading = normalize_angle(heading);
+        if let Some(last) = self.last {
+            self.orientation.drift = normalize_angle(self.orientation.heading - last.angles[2]);
+        }
+    }
+
+    // None until the first reading
+    pub fn orientation(&self) -> Option<Orientation> {
+        self.last.map(|_| self.orientation)
+    }
+}
+
+// Orientation of every robot, shared between the Monitor and its thread
+#[derive(Debug, Default)]
+pub(crate) struct Imus {
+    estimators: PerRobot<ImuEstimator>,
+}
+
+impl Imus {
+    pub fn push(&mut self, config: &ImuConfig, sample: ImuSample) -> Vec<ImuEvent> {
+        let Some(estimator) = self.estimators.get_mut(sample.id) else { return vec![]; };
+        estimator.update(config, &sample).into_iter()
+            .map(|kind| ImuEvent { id: sample.id, timestamp: sample.timestamp, kind })
+            .collect()
+    }
+
+    // Store the latest orientations on the robots
+    pub fn apply(&self, robots: &mut [Robot; MAX_NUM_ROBOTS]) {
+        for (robot, (_, estimator)) in robots.iter_mut().zip(self.estimators.iter()) {
+            robot.set_orientation(estimator.orientation());
+        }
+    }
+
+    pub fn set_heading(&mut self, id: Radio_SSL_ID, heading: f32) -> Result<(), ()> {
+        self.estimators.get_mut(id).ok_or(())?.set_heading(heading);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod imu_tests {
+    use super::*;
+
+    fn sample(ms: u64, start: std::time::Instant, yaw_rate: f32, angles: [f32; 3]) -> ImuSample {
+        ImuSample {
+            id: 0,
+            timestamp: start + std::time::Duration::from_millis(ms),
+            angular_velocity: [0.0, 0.0, yaw_rate],
+            angles,
+        }
+    }
+
+    #[test]
+    fn heading_and_bias() {
+        let config = ImuConfig { bias_time_constant: std::time::Duration::from_secs(1), ..Default::default() };
+        let start = std::time::Instant::now();
+        let mut imu = ImuEstimator::default();
+        assert_eq!(imu.orientation(), None);
+
+        // Standing still with a biased gyro
+        for i in 0..500 {
+            assert!(imu.update(&config, &sample(10 * i, start, 0.02, [0.0, 0.0, 0.5])).is_empty());
+        }
+        let orientation = imu.orientation().unwrap();
+        assert!((orientation.bias - 0.02).abs() < 1e-3);
+        assert!((orientation.heading - 0.5).abs() < 0.05);
+
+        // Turning at 1 rad/s for a second
+        for i in 0..100 {
+            imu.update(&config, &sample(5000 + 10 * (i + 1), start, 1.02, [0.0, 0.0, 1.5]));
+        }
+        let orientation = imu.orientation().unwrap();
+        assert!((orientation.bias - 0.02).abs() < 1e-3);
+        assert!((orientation.heading - 1.5).abs() < 0.06);
+        assert!(orientation.drift.abs() < 0.06);
+
+        imu.set_heading(0.0);
+        assert!((imu.orientation().unwrap().drift + 1.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn collision_and_tilt() {
+        let config = ImuConfig::default();
+        let start = std::time::Instant::now();
+        let mut imu = ImuEstimator::default();
+
+        imu.update(&config, &sample(0, start, 0.0, [0.0, 0.0, 0.0]));
+        assert!(imu.update(&config, &sample(10, start, 0.0, [0.01, 0.0, 0.0])).is_empty());
+
+        // A jolt of 0.1 rad in 10 ms, the bounce back is the same collision
+        assert_eq!(imu.update(&config, &sample(20, start, 0.0, [0.11, 0.0, 0.0])).len(), 1);
+        assert!(imu.update(&config, &sample(30, start, 0.0, [0.01, 0.0, 0.0])).is_empty());
+        assert_eq!(imu.orientation().unwrap().last_collision, Some(start + std::time::Duration::from_millis(20)));
+
+        // Slowly tipping over and back
+        let mut events = vec![];
+        for i in 0..40 {
+            let pitch = 0.01 * i.min(40 - i) as f32;
+            events.extend(imu.update(&config, &sample(1000 + 100 * i, start, 0.0, [0.01, pitch, 0.0])));
+        }
+        assert!(matches!(events.as_slice(), [ImuEventKind::TiltStarted { .. }, ImuEventKind::TiltEnded]));
+    }
+}
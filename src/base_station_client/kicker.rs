@@ -0,0 +1,248 @@
+use super::robot::Robot;
+use super::tracker::{LastSeen, RobotTracker};
+use crate::glue::{HG_Status, Radio_SSL_ID};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KickerConfig {
+    // [F]
+    pub capacitance: f32,
+    // Voltage the charger stops at [V]
+    pub full_voltage: f32,
+    // Kicks are ready from this voltage on [V]
+    pub ready_voltage: f32,
+    // A drop of at least this much between two readings is a kick [V]
+    pub kick_drop: f32,
+    // Counter changes and voltage drops this close together are the same kick
+    pub kick_window: std::time::Duration,
+    // Time constant of the low pass filter on the charge rate
+    pub rate_time_constant: std::time::Duration,
+}
+
+impl Default for KickerConfig {
+    fn default() -> Self {
+        KickerConfig {
+            capacitance: 0.0044,
+            full_voltage: 200.0,
+            ready_voltage: 180.0,
+            kick_drop: 20.0,
+            kick_window: std::time::Duration::from_millis(300),
+            rate_time_constant: std::time::Duration::from_millis(500),
+        }
+    }
+}
+
+impl KickerConfig {
+    // Energy stored in the capacitor at a voltage [J]
+    pub fn energy(&self, voltage: f32) -> f32 {
+        0.5 * self.capacitance * voltage * voltage
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KickSource {
+    Voltage,
+    ReflexCounter,
+    SmartCounter,
+}
+
+// Sent as an event when a robot kicked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KickEvent {
+    pub id: Radio_SSL_ID,
+    pub time: std::time::Instant,
+    // What noticed the kick first
+    pub source: KickSource,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct KickerEstimate {
+    // [V]
+    pub voltage: f32,
+    pub status: Option<HG_Status>,
+    // Filtered, negative while discharging [V/s]
+    pub charge_rate: f32,
+    pub charging: bool,
+    // None if full, or not charging
+    pub time_to_full: Option<std::time::Duration>,
+    // Stored in the capacitor [J]
+    pub energy: f32,
+    // Voltage at or above KickerConfig::ready_voltage and the kicker reports no fault
+    pub ready: bool,
+    pub last_kick: Option<std::time::Instant>,
+}
+
+impl KickerEstimate {
+    pub fn kick_ready(&self) -> bool {
+        self.ready
+    }
+}
+
+// Capacitor charge and kicks of one robot
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct KickerModel {
+    estimate: KickerEstimate,
+    // (time, voltage) of the last capacitor reading
+    last_voltage: Option<(std::time::Instant, f32)>,
+    // (reflex, smart) kick counters of the last status
+    last_counters: Option<(u8, u8)>,
+}
+
+impl KickerModel {
+    // Add a capacitor reading from a PrimaryStatusLF, returns the source if it shows a kick
+    pub fn update_voltage(
+        &mut self,
+        config: &KickerConfig,
+        voltage: f32,
+        status: HG_Status,
+        time: std::time::Instant,
+    ) -> Option<KickSource> {
+        let mut kicked = None;
+        if let Some((last_time, last_voltage)) = self.last_voltage {
+            let dt = time.saturating_duration_since(last_time).as_secs_f32();
+            if last_voltage - voltage >= config.kick_drop {
+                kicked = self.kick(config, KickSource::Voltage, time);
+                // A discharge says nothing about the charger
+                self.estimate.charge_rate = 0.0;
+            } else if dt > 0.0 {
+                let rate = (voltage - last_voltage) / dt;
+                let tau = config.rate_time_constant.as_secs_f32();
+                let alpha = if tau > 0.0 { 1.0 - (-dt / tau).exp() } else { 1.0 };
+                self.estimate.charge_rate += alpha * (rate - self.estimate.charge_rate);
+            }
+        }
+        self.last_voltage = Some((time, voltage));
+
+        let full = voltage >= config.full_voltage;
+        self.estimate.voltage = voltage;
+        self.estimate.status = Some(status);
+        self.estimate.charging = !full && self.estimate.charge_rate > 0.0;
+        // The filtered rate decays towards zero while the voltage holds, the time can overflow
+        self.estimate.time_to_full = self.estimate.charging
+            .then(|| std::time::Duration::try_from_secs_f32((config.full_voltage - voltage) / self.estimate.charge_rate).ok())
+            .flatten();
+        self.estimate.energy = config.energy(voltage);
+        self.estimate.ready = voltage >= config.ready_voltage && matches!(status, HG_Status::OK | HG_Status::ARMED);
+        kicked
+    }
+
+    // Add the kick counters from a PrimaryStatusHF, returns the source if they show a kick
+    pub fn update_counters(&mut self, config: &KickerConfig, reflex: u8, smart: u8, time: std::time::Instant) -> Option<KickSource> {
+        let last = self.last_counters.replace((reflex, smart));
+        let (last_reflex, last_smart) = last?;
+        if reflex != last_reflex {
+            self.kick(config, KickSource::ReflexCounter, time)
+        } else if smart != last_smart {
+            self.kick(config, KickSource::SmartCounter, time)
+        } else {
+            None
+        }
+    }
+
+    // None if the kick was already noticed by another source
+    fn kick(&mut self, config: &KickerConfig, source: KickSource, time: std::time::Instant) -> Option<KickSource> {
+        let known = self.estimate.last_kick.is_some_and(|last| time.saturating_duration_since(last) <= config.kick_window);
+        self.estimate.last_kick = Some(time);
+        // The capacitor is drained until the next reading says otherwise
+        self.estimate.ready = false;
+        (!known).then_some(source)
+    }
+
+    pub fn estimate(&self) -> KickerEstimate {
+        self.estimate
+    }
+}
+
+// Kicker model of a robot, kept by the monitor thread
+#[derive(Debug, Default)]
+pub(crate) struct KickerTracker {
+    model: KickerModel,
+    last_status_hf: LastSeen,
+    last_status_lf: LastSeen,
+}
+
+impl KickerTracker {
+    // None until the robot reported its capacitor voltage
+    pub fn estimate(&self) -> Option<KickerEstimate> {
+        self.model.last_voltage.map(|_| self.model.estimate())
+    }
+}
+
+impl RobotTracker for KickerTracker {
+    type Config = KickerConfig;
+    type Event = KickEvent;
+
+    // Add the new statuses, returns the kicks noticed
+    fn update(&mut self, config: &KickerConfig, id: Radio_SSL_ID, robot: &mut Robot) -> Vec<KickEvent> {
+        let mut kicks = vec![];
+        if let (Some(reflex), Some(smart)) = (robot.reflex_kick_counter(), robot.smart_kick_counter()) {
+            if let Some(received) = self.last_status_hf.take_new(robot.status_hf_timestamp()) {
+                kicks.extend(self.model.update_counters(config, reflex, smart, received).map(|source| (received, source)));
+            }
+        }
+        if let (Some(voltage), Some(status)) = (robot.kicker_cap_voltage(), robot.kicker_status()) {
+            if let Some(received) = self.last_status_lf.take_new(robot.status_lf_timestamp()) {
+                kicks.extend(self.model.update_voltage(config, voltage, status, received).map(|source| (received, source)));
+            }
+        }
+        kicks.into_iter().map(|(time, source)| KickEvent { id, time, source }).collect()
+    }
+}
+
+#[cfg(test)]
+mod kicker_tests {
+    use super::*;
+
+    #[test]
+    fn charge_and_kick() {
+        let config = KickerConfig::default();
+        let start = std::time::Instant::now();
+        let ms = |ms| start + std::time::Duration::from_millis(ms);
+        let mut kicker = KickerModel::default();
+
+        // Charging at 100 V/s
+        for i in 0..20 {
+            assert_eq!(kicker.update_voltage(&config, 10.0 * i as f32, HG_Status::OK, ms(100 * i)), None);
+        }
+        let estimate = kicker.estimate();
+        assert!(estimate.charging && estimate.ready);
+        assert!((estimate.charge_rate - 100.0).abs() < 5.0);
+        let time_to_full = estimate.time_to_full.unwrap().as_secs_f32();
+        assert!((time_to_full - 0.1).abs() < 0.01);
+        assert!((estimate.energy - 0.5 * 0.0044 * 190.0 * 190.0).abs() < 1e-3);
+
+        kicker.update_voltage(&config, 200.0, HG_Status::OK, ms(2000));
+        assert!(!kicker.estimate().charging && kicker.estimate().time_to_full.is_none());
+
+        // The counter notices the kick, the voltage drop after it is the same kick
+        assert_eq!(kicker.update_counters(&config, 3, 7, ms(2000)), None);
+        assert_eq!(kicker.update_counters(&config, 4, 7, ms(2050)), Some(KickSource::ReflexCounter));
+        assert!(!kicker.estimate().kick_ready());
+        assert_eq!(kicker.update_voltage(&config, 40.0, HG_Status::OK, ms(2100)), None);
+        assert_eq!(kicker.estimate().last_kick, Some(ms(2100)));
+
+        // A drop on its own is a kick too
+        assert_eq!(kicker.update_voltage(&config, 195.0, HG_Status::OK, ms(4000)), None);
+        assert!(kicker.estimate().ready);
+        assert_eq!(kicker.update_voltage(&config, 20.0, HG_Status::OK, ms(4100)), Some(KickSource::Voltage));
+
+        // Not ready with a fault, whatever the voltage
+        kicker.update_voltage(&config, 200.0, HG_Status::EMERGENCY, ms(6000));
+        assert!(!kicker.estimate().ready);
+    }
+
+    #[test]
+    fn holding_below_full() {
+        let config = KickerConfig::default();
+        let start = std::time::Instant::now();
+        let mut kicker = KickerModel::default();
+
+        // The charger settles just below full_voltage, the charge rate decays for minutes
+        kicker.update_voltage(&config, 190.0, HG_Status::OK, start);
+        for i in 1..3000 {
+            kicker.update_voltage(&config, 199.0, HG_Status::OK, start + std::time::Duration::from_millis(100 * i));
+        }
+        let estimate = kicker.estimate();
+        assert!(estimate.ready);
+        assert!(estimate.time_to_full.is_none());
+    }
+}
@@ -0,0 +1,121 @@
+// Omni-wheel geometry. Motors 0 to 3 drive the wheels, motor 4 optionally drives the dribbler.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Kinematics {
+    // Angle of each wheel position, counterclockwise from forward (+x) [rad]
+    pub wheel_angles: [f32; 4],
+    // [m]
+    pub wheel_radius: f32,
+    // Distance from the centre of the robot to the wheels [m]
+    pub robot_radius: f32,
+    // Motor speed per dribbler speed if motor 4 drives the dribbler, None if it's not used
+    pub dribbler_ratio: Option<f32>,
+}
+
+impl Default for Kinematics {
+    fn default() -> Self {
+        Kinematics {
+            wheel_angles: [30.0f32, 150.0, 225.0, 315.0].map(f32::to_radians),
+            wheel_radius: 0.027,
+            robot_radius: 0.081,
+            dribbler_ratio: Some(1.0),
+        }
+    }
+}
+
+impl Kinematics {
+    // Row of the wheel speed equation of each wheel, multiplied by the wheel radius
+    fn rows(&self) -> [[f32; 3]; 4] {
+        self.wheel_angles.map(|angle| [-angle.sin(), angle.cos(), self.robot_radius])
+    }
+
+    // Inverse kinematics: wheel speeds [rad/s] for a body velocity, x and y in m/s, w in rad/s.
+    // A positive wheel speed drives the robot counterclockwise.
+    pub fn wheel_speeds(&self, x: f32, y: f32, w: f32) -> [f32; 4] {
+        self.rows().map(|row| (row[0] * x + row[1] * y + row[2] * w) / self.wheel_radius)
+    }
+
+    // All 5 motor speeds [rad/s], including the dribbler speed [rad/s] on motor 4
+    pub fn motor_speeds(&self, x: f32, y: f32, w: f32, dribbler_speed: f32) -> [f32; 5] {
+        let [a, b, c, d] = self.wheel_speeds(x, y, w);
+        [a, b, c, d, self.dribbler_ratio.map_or(0.0, |ratio| dribbler_speed * ratio)]
+    }
+
+    // Forward kinematics: the body velocity (x, y [m/s], w [rad/s]) that best fits the speeds of
+    // the 4 wheels. Four wheels over-determine the velocity, the rest is slip or measurement noise.
+    pub fn body_velocity(&self, wheel_speeds: &[f32]) -> Option<(f32, f32, f32)> {
+        if wheel_speeds.len() < 4 { return None; }
+        let rows = self.rows();
+
+        // Least squares, solve (J^T J) v = J^T (r * speeds)
+        let mut jtj = [[0.0f32; 3]; 3];
+        let mut jts = [0.0f32; 3];
+        for (row, &speed) in rows.iter().zip(wheel_speeds) {
+            for i in 0..3 {
+                for j in 0..3 {
+                    jtj[i][j] += row[i] * row[j];
+                }
+                jts[i] += row[i] * speed * self.wheel_radius;
+            }
+        }
+        let [x, y, w] = solve3(jtj, jts)?;
+        Some((x, y, w))
+    }
+
+    // Dribbler speed [rad/s] from the motor speeds, None if motor 4 doesn't drive the dribbler
+    pub fn dribbler_speed(&self, motor_speeds: &[f32; 5]) -> Option<f32> {
+        let ratio = self.dribbler_ratio?;
+        if ratio == 0.0 { return None; }
+        Some(motor_speeds[4] / ratio)
+    }
+}
+
+// Cramer's rule, None if the matrix is singular
+fn solve3(m: [[f32; 3]; 3], b: [f32; 3]) -> Option<[f32; 3]> {
+    let det = |m: &[[f32; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let d = det(&m);
+    if d.abs() < 1e-9 { return None; }
+    let mut result = [0.0; 3];
+    for (column, value) in result.iter_mut().enumerate() {
+        let mut replaced = m;
+        for row in 0..3 {
+            replaced[row][column] = b[row];
+        }
+        *value = det(&replaced) / d;
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod kinematics_tests {
+    use super::*;
+
+    #[test]
+    fn inverse() {
+        let kinematics = Kinematics::default();
+        let rotation = kinematics.wheel_speeds(0.0, 0.0, 1.0);
+        for speed in rotation {
+            assert!((speed - 3.0).abs() < 1e-5);
+        }
+        let forward = kinematics.wheel_speeds(1.0, 0.0, 0.0);
+        assert!(forward[0] < 0.0 && forward[1] < 0.0 && forward[2] > 0.0 && forward[3] > 0.0);
+        assert_eq!(kinematics.motor_speeds(0.0, 0.0, 0.0, 50.0)[4], 50.0);
+        assert_eq!(Kinematics { dribbler_ratio: None, ..kinematics }.motor_speeds(0.0, 0.0, 0.0, 50.0)[4], 0.0);
+    }
+
+    #[test]
+    fn round_trip() {
+        let kinematics = Kinematics::default();
+        for (x, y, w) in [(1.0, 0.0, 0.0), (0.3, -1.2, 2.0), (0.0, 0.0, -4.0)] {
+            let speeds = kinematics.motor_speeds(x, y, w, 100.0);
+            let (bx, by, bw) = kinematics.body_velocity(&speeds).unwrap();
+            assert!((bx - x).abs() < 1e-4 && (by - y).abs() < 1e-4 && (bw - w).abs() < 1e-4);
+            assert_eq!(kinematics.dribbler_speed(&speeds), Some(100.0));
+        }
+        let degenerate = Kinematics { wheel_angles: [0.0; 4], ..kinematics };
+        assert!(degenerate.body_velocity(&[1.0; 4]).is_none());
+    }
+}
@@ -0,0 +1,259 @@
+use super::tracker::PerRobot;
+use crate::glue::{Radio_Command, Radio_GlobalCommand, Radio_SSL_ID};
+
+// Identifies a sent command by its content, robots echo the command they received unchanged.
+// RoundTrips::tag_command puts a sequence number in the padding of every command sent to a
+// robot, so identical commands get different tags and a lost one isn't matched to a later echo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CommandTag(u64);
+
+impl CommandTag {
+    fn of_bytes(kind: u8, bytes: &[u8]) -> Self {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        kind.hash(&mut hasher);
+        bytes.hash(&mut hasher);
+        CommandTag(hasher.finish())
+    }
+
+    pub fn of_command(command: &Radio_Command) -> Self {
+        use zerocopy::AsBytes;
+        Self::of_bytes(0, command.as_bytes())
+    }
+
+    pub fn of_global_command(gcommand: &Radio_GlobalCommand) -> Self {
+        use zerocopy::AsBytes;
+        Self::of_bytes(1, gcommand.as_bytes())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyConfig {
+    // Commands not echoed within this time count as never echoed
+    pub echo_timeout: std::time::Duration,
+    // Round trips the statistics are computed over
+    pub samples: usize,
+}
+
+impl Default for LatencyConfig {
+    fn default() -> Self {
+        LatencyConfig {
+            echo_timeout: std::time::Duration::from_millis(500),
+            samples: 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencyStats {
+    // Over the last LatencyConfig::samples round trips, None before the first echo
+    pub min: Option<std::time::Duration>,
+    pub mean: Option<std::time::Duration>,
+    pub p95: Option<std::time::Duration>,
+    pub max: Option<std::time::Duration>,
+    pub samples: usize,
+    // Since the monitor started
+    pub sent: u64,
+    pub echoed: u64,
+    pub never_echoed: u64,
+}
+
+impl LatencyStats {
+    // Fraction of the commands that timed out or were echoed, which timed out
+    pub fn never_echoed_fraction(&self) -> Option<f32> {
+        let resolved = self.echoed + self.never_echoed;
+        (resolved > 0).then(|| self.never_echoed as f32 / resolved as f32)
+    }
+}
+
+// Command round trips of one robot
+#[derive(Debug, Clone, Default)]
+pub struct RoundTrips {
+    // Sent commands waiting for their echo, oldest first
+    pending: std::collections::VecDeque<(CommandTag, std::time::Instant)>,
+    latencies: std::collections::VecDeque<std::time::Duration>,
+    // Put in the padding of the next command, wraps long after a command times out
+    sequence: u8,
+    sent: u64,
+    echoed: u64,
+    never_echoed: u64,
+}
+
+impl RoundTrips {
+    fn next_sequence(&mut self) -> u8 {
+        self.sequence = self.sequence.wrapping_add(1);
+        self.sequence
+    }
+
+    // Number a command about to be sent, returns the tag its echo will have
+    pub fn tag_command(&mut self, command: &mut Radio_Command) -> CommandTag {
+        command._pad = [0; 8];
+        command._pad[0] = self.next_sequence();
+        CommandTag::of_command(command)
+    }
+
+    pub fn tag_global_command(&mut self, gcommand: &mut Radio_GlobalCommand) -> CommandTag {
+        gcommand._pad = self.next_sequence();
+        CommandTag::of_global_command(gcommand)
+    }
+
+    pub fn sent(&mut self, config: &LatencyConfig, tag: CommandTag, time: std::time::Instant) {
+        self.expire(config, time);
+        self.pending.push_back((tag, time));
+        self.sent += 1;
+    }
+
+    // Returns the round trip time if the echo matches a sent command
+    pub fn echoed(&mut self, config: &LatencyConfig, tag: CommandTag, time: std::time::Instant) -> Option<std::time::Duration> {
+        self.expire(config, time);
+        let index = self.pending.iter().position(|(pending, _)| *pending == tag)?;
+        let (_, sent) = self.pending.remove(index)?;
+        let latency = time.saturating_duration_since(sent);
+        self.echoed += 1;
+        self.latencies.push_back(latency);
+        while self.latencies.len() > config.samples.max(1) {
+            self.latencies.pop_front();
+        }
+        Some(latency)
+    }
+
+    // Count the commands that waited longer than the echo timeout as never echoed
+    pub fn expire(&mut self, config: &LatencyConfig, now: std::time::Instant) {
+        while self.pending.front().is_some_and(|(_, sent)| now.saturating_duration_since(*sent) > config.echo_timeout) {
+            self.pending.pop_front();
+            self.never_echoed += 1;
+        }
+    }
+
+    pub fn stats(&self) -> LatencyStats {
+        let mut sorted: Vec<std::time::Duration> = self.latencies.iter().copied().collect();
+        sorted.sort();
+        let samples = sorted.len();
+        LatencyStats {
+            min: sorted.first().copied(),
+            mean: (samples > 0).then(|| sorted.iter().sum::<std::time::Duration>() / samples as u32),
+            p95: (samples > 0).then(|| sorted[(samples * 95).div_ceil(100) - 1]),
+            max: sorted.last().copied(),
+            samples,
+            sent: self.sent,
+            echoed: self.echoed,
+            never_echoed: self.never_echoed,
+        }
+    }
+}
+
+// Command round trips of every robot, shared between the Monitor and its thread
+#[derive(Debug, Default)]
+pub(crate) struct Latencies {
+    robots: PerRobot<RoundTrips>,
+}
+
+impl Latencies {
+    // Broadcasts aren't tracked, every robot echoes them. None for those.
+    pub fn tag_command(&mut self, id: Radio_SSL_ID, command: &mut Radio_Command) -> Option<CommandTag> {
+        Some(self.robots.get_mut(id)?.tag_command(command))
+    }
+
+    pub fn tag_global_command(&mut self, id: Radio_SSL_ID, gcommand: &mut Radio_GlobalCommand) -> Option<CommandTag> {
+        Some(self.robots.get_mut(id)?.tag_global_command(gcommand))
+    }
+
+    pub fn sent(&mut self, config: &LatencyConfig, id: Radio_SSL_ID, tag: CommandTag, time: std::time::Instant) {
+        let Some(robot) = self.robots.get_mut(id) else { return; };
+        robot.sent(config, tag, time);
+    }
+
+    pub fn echoed(&mut self, config: &LatencyConfig, id: Radio_SSL_ID, tag: CommandTag, time: std::time::Instant) {
+        let Some(robot) = self.robots.get_mut(id) else { return; };
+        robot.echoed(config, tag, time);
+    }
+
+    pub fn expire(&mut self, config: &LatencyConfig, now: std::time::Instant) {
+        for (_, robot) in self.robots.iter_mut() {
+            robot.expire(config, now);
+        }
+    }
+
+    pub fn stats(&self, id: Radio_SSL_ID) -> Option<LatencyStats> {
+        Some(self.robots.get(id)?.stats())
+    }
+}
+
+#[cfg(test)]
+mod latency_tests {
+    use super::*;
+    use crate::base_station_client::command::{GlobalCommand, RobotCommand};
+    use crate::base_station_client::field::FieldFrame;
+
+    #[test]
+    fn round_trips() {
+        let config = LatencyConfig::default();
+        let start = std::time::Instant::now();
+        let ms = |ms| start + std::time::Duration::from_millis(ms);
+        let forward = RobotCommand::new().velocity(1.0, 0.0, 0.0).to_command();
+        let turn = RobotCommand::new().velocity(0.0, 0.0, 1.0).to_command();
+        assert_ne!(CommandTag::of_command(&forward), CommandTag::of_command(&turn));
+
+        let mut round_trips = RoundTrips::default();
+        assert_eq!(round_trips.stats().mean, None);
+        assert_eq!(round_trips.stats().never_echoed_fraction(), None);
+
+        // The robot echoes the numbered command it received
+        let send = |round_trips: &mut RoundTrips, command: &Radio_Command, time| {
+            let mut command = *command;
+            let tag = round_trips.tag_command(&mut command);
+            round_trips.sent(&config, tag, time);
+            command
+        };
+        let echo = |command: &Radio_Command| CommandTag::of_command(command);
+
+        let first = send(&mut round_trips, &forward, ms(0));
+        let second = send(&mut round_trips, &forward, ms(10));
+        let third = send(&mut round_trips, &turn, ms(20));
+        assert_ne!(echo(&first), echo(&second));
+        assert_eq!(round_trips.echoed(&config, echo(&third), ms(25)), Some(std::time::Duration::from_millis(5)));
+        assert_eq!(round_trips.echoed(&config, echo(&second), ms(30)), Some(std::time::Duration::from_millis(20)));
+        // An echo that matches nothing is ignored
+        assert_eq!(round_trips.echoed(&config, echo(&third), ms(35)), None);
+
+        // The first forward command is never echoed
+        round_trips.expire(&config, ms(1000));
+        let stats = round_trips.stats();
+        assert_eq!((stats.sent, stats.echoed, stats.never_echoed), (3, 2, 1));
+        assert!((stats.never_echoed_fraction().unwrap() - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(stats.min, Some(std::time::Duration::from_millis(5)));
+        assert_eq!(stats.max, Some(std::time::Duration::from_millis(20)));
+        assert_eq!(stats.mean, Some(std::time::Duration::from_micros(12500)));
+
+        // Round trips of 1 to 100 ms
+        let mut round_trips = RoundTrips::default();
+        for i in 1..=100 {
+            let command = send(&mut round_trips, &forward, ms(10 * i));
+            round_trips.echoed(&config, echo(&command), ms(11 * i));
+        }
+        assert_eq!(round_trips.stats().p95, Some(std::time::Duration::from_millis(95)));
+    }
+
+    #[test]
+    fn lost_identical_command() {
+        let config = LatencyConfig::default();
+        let start = std::time::Instant::now();
+        let ms = |ms| start + std::time::Duration::from_millis(ms);
+        let mut round_trips = RoundTrips::default();
+        let mut gcommand = GlobalCommand::new().velocity(1.0, 0.0).heading(0.5).to_global_command(FieldFrame::default(), 0.0);
+
+        // The same global command every 10 ms, echoed after 5 ms, the second one is lost
+        for i in 0..5 {
+            let tag = round_trips.tag_global_command(&mut gcommand);
+            round_trips.sent(&config, tag, ms(10 * i));
+            if i != 1 {
+                assert_eq!(round_trips.echoed(&config, CommandTag::of_global_command(&gcommand), ms(10 * i + 5)),
+                    Some(std::time::Duration::from_millis(5)));
+            }
+        }
+        round_trips.expire(&config, ms(1000));
+        let stats = round_trips.stats();
+        assert_eq!((stats.sent, stats.echoed, stats.never_echoed), (5, 4, 1));
+        assert_eq!(stats.max, Some(std::time::Duration::from_millis(5)));
+    }
+}
@@ -0,0 +1,330 @@
+use super::basestation::MAX_NUM_ROBOTS;
+use super::scaling;
+use crate::glue::{Radio_Command, Radio_GenericCommand, Radio_GlobalCommand, Radio_SSL_ID};
+
+// What to do with a command that exceeds a limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitPolicy {
+    // Reduce the value to the limit and send the command
+    Clamp,
+    // Don't send the command at all
+    Reject,
+    // Send the command unchanged, only report the violation
+    Warn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    pub max: f32,
+    pub policy: LimitPolicy,
+}
+
+impl Limit {
+    pub fn clamp(max: f32) -> Self {
+        Limit { max, policy: LimitPolicy::Clamp }
+    }
+
+    pub fn reject(max: f32) -> Self {
+        Limit { max, policy: LimitPolicy::Reject }
+    }
+
+    pub fn warn(max: f32) -> Self {
+        Limit { max, policy: LimitPolicy::Warn }
+    }
+}
+
+// Physical limits for outgoing commands. None means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CommandLimits {
+    // Length of the (x, y) velocity [m/s]
+    pub linear_speed: Option<Limit>,
+    // [rad/s]
+    pub angular_speed: Option<Limit>,
+    // Change of the (x, y) velocity between consecutive commands to a robot [m/s^2]
+    pub linear_acceleration: Option<Limit>,
+    // Change of the angular velocity between consecutive commands to a robot [rad/s^2]
+    pub angular_acceleration: Option<Limit>,
+    // [rad/s]
+    pub dribbler_speed: Option<Limit>,
+    // [ms]
+    pub kick_time: Option<Limit>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKind {
+    LinearSpeed,
+    AngularSpeed,
+    LinearAcceleration,
+    AngularAcceleration,
+    DribblerSpeed,
+    KickTime,
+    // Heading setpoint or measurement of a global command, only checked for NaN and infinity
+    Heading,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LimitViolation {
+    pub id: Radio_SSL_ID,
+    pub kind: LimitKind,
+    // Requested value, in the unit of the limit
+    pub value: f32,
+    pub max: f32,
+    pub policy: LimitPolicy,
+}
+
+// Applies CommandLimits to the command stream, remembering the last command sent to every robot
+// to limit acceleration
+#[derive(Debug, Clone)]
+pub struct CommandLimiter {
+    pub limits: CommandLimits,
+    // (time, x, y, w) of the last command passed for each robot, local and global frame
+    last_command: [Option<(std::time::Instant, f32, f32, f32)>; MAX_NUM_ROBOTS],
+    last_global_command: [Option<(std::time::Instant, f32, f32, f32)>; MAX_NUM_ROBOTS],
+}
+
+// Outcome of checking a command
+#[derive(Debug, Clone)]
+pub struct Checked<T> {
+    // None if the command was rejected
+    pub command: Option<T>,
+    pub violations: Vec<LimitViolation>,
+}
+
+struct Check<'a> {
+    id: Radio_SSL_ID,
+    violations: &'a mut Vec<LimitViolation>,
+    rejected: bool,
+}
+
+impl Check<'_> {
+    // Returns the value to use, which is only different from value when clamping. NaN and
+    // infinity are rejected whatever the policy, even without a limit.
+    fn apply(&mut self, limit: Option<Limit>, kind: LimitKind, value: f32) -> f32 {
+        if !value.is_finite() {
+            let max = limit.map_or(f32::INFINITY, |limit| limit.max);
+            self.violations.push(LimitViolation { id: self.id, kind, value, max, policy: LimitPolicy::Reject });
+            self.rejected = true;
+            return value;
+        }
+        let Some(limit) = limit else { return value; };
+        if value.abs() <= limit.max {
+            return value;
+        }
+        self.violations.push(LimitViolation { id: self.id, kind, value, max: limit.max, policy: limit.policy });
+        match limit.policy {
+            LimitPolicy::Clamp => limit.max * value.signum(),
+            LimitPolicy::Reject => {
+                self.rejected = true;
+                value
+            }
+            LimitPolicy::Warn => value,
+        }
+    }
+}
+
+impl CommandLimiter {
+    pub fn new(limits: CommandLimits) -> Self {
+        CommandLimiter {
+            limits,
+            last_command: [None; MAX_NUM_ROBOTS],
+            last_global_command: [None; MAX_NUM_ROBOTS],
+        }
+    }
+
+    fn check_generic(&self, check: &mut Check, gen_command: &mut Radio_GenericCommand) {
+        let dribbler_speed = scaling::DRIBBLER_SPEED.decode(gen_command.dribbler_speed_i);
+        let limited = check.apply(self.limits.dribbler_speed, LimitKind::DribblerSpeed, dribbler_speed);
+        if limited != dribbler_speed {
+            gen_command.dribbler_speed_i = scaling::DRIBBLER_SPEED.encode(limited).raw;
+        }
+
+        let kick_time = scaling::KICK_TIME.decode(gen_command.kick_time_i);
+        let limited = check.apply(self.limits.kick_time, LimitKind::KickTime, kick_time);
+        if limited != kick_time {
+            gen_command.kick_time_i = scaling::KICK_TIME.encode(limited).raw;
+        }
+    }
+
+    // Limits speed and acceleration of (x, y, w), returns the velocity to use
+    fn check_velocity(
+        &self,
+        check: &mut Check,
+        last: Option<(std::time::Instant, f32, f32, f32)>,
+        now: std::time::Instant,
+        (mut x, mut y, mut w): (f32, f32, f32),
+        limit_w: bool,
+    ) -> (f32, f32, f32) {
+        let speed = x.hypot(y);
+        let limited = check.apply(self.limits.linear_speed, LimitKind::LinearSpeed, speed);
+        if limited < speed {
+            x *= limited / speed;
+            y *= limited / speed;
+        }
+        if limit_w {
+            w = check.apply(self.limits.angular_speed, LimitKind::AngularSpeed, w);
+        }
+
+        if let Some((time, last_x, last_y, last_w)) = last {
+            let dt = now.saturating_duration_since(time).as_secs_f32();
+            if dt > 0.0 {
+                let (dx, dy) = (x - last_x, y - last_y);
+                let acceleration = dx.hypot(dy) / dt;
+                let limited = check.apply(self.limits.linear_acceleration, LimitKind::LinearAcceleration, acceleration);
+                if limited < acceleration {
+                    x = last_x + dx * limited / acceleration;
+                    y = last_y + dy * limited / acceleration;
+                }
+                if limit_w {
+                    let acceleration = (w - last_w) / dt;
+                    let limited = check.apply(self.limits.angular_acceleration, LimitKind::AngularAcceleration, acceleration);
+                    w = last_w + limited * dt;
+                }
+            }
+        }
+        (x, y, w)
+    }
+
+    pub fn check_command(&mut self, id: Radio_SSL_ID, mut command: Radio_Command, now: std::time::Instant) -> Checked<Radio_Command> {
+        let mut violations = vec![];
+        let mut check = Check { id, violations: &mut violations, rejected: false };
+        let last = self.last_command.get(id as usize).copied().flatten();
+
+        let (x, y, w) = self.check_velocity(&mut check, last, now, (command.speed.x, command.speed.y, command.speed.z), true);
+        self.check_generic(&mut check, &mut command.gen_command);
+        let rejected = check.rejected;
+        if rejected {
+            return Checked { command: None, violations };
+        }
+
+        (command.speed.x, command.speed.y, command.speed.z) = (x, y, w);
+        if let Some(last) = self.last_command.get_mut(id as usize) {
+            *last = Some((now, x, y, w));
+        }
+        Checked { command: Some(command), violations }
+    }
+
+    pub fn check_global_command(&mut self, id: Radio_SSL_ID, mut gcommand: Radio_GlobalCommand, now: std::time::Instant) -> Checked<Radio_GlobalCommand> {
+        let mut violations = vec![];
+        let mut check = Check { id, violations: &mut violations, rejected: false };
+        let last = self.last_global_command.get(id as usize).copied().flatten();
+
+        // The robot controls its own rotation towards the heading setpoint
+        let (x, y, _) = self.check_velocity(&mut check, last, now, (gcommand.global_speed_x, gcommand.global_speed_y, 0.0), false);
+        self.check_generic(&mut check, &mut gcommand.gen_command);
+        check.apply(None, LimitKind::Heading, gcommand.heading_setpoint);
+        check.apply(None, LimitKind::Heading, gcommand.heading_last_measurement);
+        let rejected = check.rejected;
+        if rejected {
+            return Checked { command: None, violations };
+        }
+
+        (gcommand.global_speed_x, gcommand.global_speed_y) = (x, y);
+        if let Some(last) = self.last_global_command.get_mut(id as usize) {
+            *last = Some((now, x, y, 0.0));
+        }
+        Checked { command: Some(gcommand), violations }
+    }
+}
+
+impl Default for CommandLimiter {
+    fn default() -> Self {
+        Self::new(CommandLimits::default())
+    }
+}
+
+#[cfg(test)]
+mod limits_tests {
+    use super::*;
+    use crate::base_station_client::command::RobotCommand;
+
+    #[test]
+    fn unlimited_passes_everything() {
+        let mut limiter = CommandLimiter::default();
+        let command = RobotCommand::new().velocity(40.0, 0.0, 100.0).kick_time(1000.0).to_command();
+        let checked = limiter.check_command(0, command, std::time::Instant::now());
+        assert!(checked.violations.is_empty());
+        assert_eq!(checked.command.unwrap().speed.x, 40.0);
+    }
+
+    #[test]
+    fn policies() {
+        let mut limiter = CommandLimiter::new(CommandLimits {
+            linear_speed: Some(Limit::clamp(2.0)),
+            angular_speed: Some(Limit::warn(5.0)),
+            kick_time: Some(Limit::reject(scaling::KICK_TIME.decode(10))),
+            ..Default::default()
+        });
+        let now = std::time::Instant::now();
+
+        let checked = limiter.check_command(1, RobotCommand::new().velocity(3.0, 4.0, -6.0).to_command(), now);
+        let command = checked.command.unwrap();
+        assert!((command.speed.x - 1.2).abs() < 1e-5);
+        assert!((command.speed.y - 1.6).abs() < 1e-5);
+        assert_eq!(command.speed.z, -6.0);
+        assert_eq!(checked.violations.len(), 2);
+        assert_eq!(checked.violations[0].kind, LimitKind::LinearSpeed);
+        assert_eq!(checked.violations[0].value, 5.0);
+        assert_eq!(checked.violations[1].policy, LimitPolicy::Warn);
+
+        let kick = RobotCommand::new().kick_time(scaling::KICK_TIME.decode(20)).to_command();
+        let checked = limiter.check_command(1, kick, now);
+        assert!(checked.command.is_none());
+        assert_eq!(checked.violations[0].kind, LimitKind::KickTime);
+    }
+
+    #[test]
+    fn non_finite_is_rejected() {
+        let mut limiter = CommandLimiter::new(CommandLimits {
+            linear_speed: Some(Limit::clamp(2.0)),
+            angular_speed: Some(Limit::warn(5.0)),
+            ..Default::default()
+        });
+        let now = std::time::Instant::now();
+
+        for velocity in [(f32::NAN, 0.0, 0.0), (0.0, 0.0, f32::NAN), (0.0, f32::INFINITY, 0.0)] {
+            let command = RobotCommand::new().velocity(velocity.0, velocity.1, velocity.2).to_command();
+            let checked = limiter.check_command(0, command, now);
+            assert!(checked.command.is_none());
+            assert_eq!(checked.violations[0].policy, LimitPolicy::Reject);
+        }
+
+        // Also without a limit, and for global commands
+        let mut command = RobotCommand::new().to_command();
+        command.speed.x = f32::NAN;
+        assert!(CommandLimiter::default().check_command(0, command, now).command.is_none());
+        let mut gcommand = crate::GlobalCommand::new().to_global_command(Default::default(), 0.0);
+        gcommand.global_speed_y = f32::NEG_INFINITY;
+        assert!(limiter.check_global_command(0, gcommand, now).command.is_none());
+
+        // Headings have no limit, but go to the robot's heading controller
+        for heading in [f32::NAN, f32::INFINITY] {
+            let mut gcommand = crate::GlobalCommand::new().to_global_command(Default::default(), 0.0);
+            gcommand.heading_setpoint = heading;
+            let checked = limiter.check_global_command(0, gcommand, now);
+            assert!(checked.command.is_none());
+            assert_eq!(checked.violations[0].kind, LimitKind::Heading);
+            gcommand.heading_setpoint = 0.0;
+            gcommand.heading_last_measurement = heading;
+            assert!(CommandLimiter::default().check_global_command(0, gcommand, now).command.is_none());
+        }
+    }
+
+    #[test]
+    fn acceleration() {
+        let mut limiter = CommandLimiter::new(CommandLimits {
+            linear_acceleration: Some(Limit::clamp(1.0)),
+            ..Default::default()
+        });
+        let start = std::time::Instant::now();
+        let later = start + std::time::Duration::from_millis(500);
+
+        // The first command has nothing to compare against
+        assert!(limiter.check_command(2, RobotCommand::new().to_command(), start).violations.is_empty());
+        let checked = limiter.check_command(2, RobotCommand::new().velocity(2.0, 0.0, 0.0).to_command(), later);
+        assert_eq!(checked.violations[0].kind, LimitKind::LinearAcceleration);
+        assert!((checked.command.unwrap().speed.x - 0.5).abs() < 1e-5);
+
+        // Other robots are tracked separately
+        assert!(limiter.check_command(3, RobotCommand::new().velocity(2.0, 0.0, 0.0).to_command(), later).violations.is_empty());
+    }
+}
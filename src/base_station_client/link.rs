@@ -0,0 +1,233 @@
+use super::tracker::PerRobot;
+use crate::glue::Radio_SSL_ID;
+
+// Upper bounds of the buckets of the gap histogram, longer gaps go in the last bucket. Arrival
+// times are only known to a POLL_INTERVAL of the monitor thread, frames read in the same loop
+// are apart by almost nothing and the others by whole loops. The bounds are 1, 2, 5, 10, 25 and
+// 50 loops of 20 ms with half a loop of margin.
+pub const GAP_BUCKETS: [std::time::Duration; 6] = [
+    std::time::Duration::from_millis(30),
+    std::time::Duration::from_millis(50),
+    std::time::Duration::from_millis(110),
+    std::time::Duration::from_millis(210),
+    std::time::Duration::from_millis(510),
+    std::time::Duration::from_millis(1010),
+];
+
+// Kinds of frames received from a robot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkMessage {
+    StatusHF,
+    StatusLF,
+    Imu,
+    Odometry,
+    CommandEcho,
+    GlobalCommandEcho,
+}
+
+impl LinkMessage {
+    pub const ALL: [LinkMessage; 6] = [
+        Self::StatusHF, Self::StatusLF, Self::Imu, Self::Odometry, Self::CommandEcho, Self::GlobalCommandEcho,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkConfig {
+    // Frame rates, jitter and loss are computed over this window
+    pub window: std::time::Duration,
+    // Rates the robots send their statuses at, used to estimate the loss [Hz]
+    pub status_hf_rate: Option<f32>,
+    pub status_lf_rate: Option<f32>,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        LinkConfig {
+            window: std::time::Duration::from_secs(2),
+            status_hf_rate: Some(50.0),
+            status_lf_rate: Some(5.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MessageLinkStats {
+    // Received in the window, per second
+    pub fps: f32,
+    // Standard deviation of the time between frames in the window, including up to a
+    // POLL_INTERVAL of the monitor thread from when the frames were read
+    pub jitter: std::time::Duration,
+    // Number of gaps between frames per GAP_BUCKETS bucket, since the monitor started
+    pub gap_histogram: [u32; GAP_BUCKETS.len() + 1],
+    pub longest_gap: std::time::Duration,
+    pub received: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LinkQuality {
+    // In the order of LinkMessage::ALL
+    pub messages: [MessageLinkStats; 6],
+    // Fraction of the expected statuses that didn't arrive in the window, None without an expected rate
+    pub status_hf_loss: Option<f32>,
+    pub status_lf_loss: Option<f32>,
+}
+
+impl LinkQuality {
+    pub fn get(&self, message: LinkMessage) -> &MessageLinkStats {
+        &self.messages[message as usize]
+    }
+}
+
+// Arrivals of one kind of frame from one robot
+#[derive(Debug, Clone, Default)]
+struct MessageTracker {
+    window: std::collections::VecDeque<std::time::Instant>,
+    first: Option<std::time::Instant>,
+    histogram: [u32; GAP_BUCKETS.len() + 1],
+    longest_gap: std::time::Duration,
+    received: u64,
+}
+
+impl MessageTracker {
+    fn add(&mut self, config: &LinkConfig, time: std::time::Instant) {
+        if let Some(&last) = self.window.back() {
+            let gap = time.saturating_duration_since(last);
+            let bucket = GAP_BUCKETS.iter().position(|&bound| gap <= bound).unwrap_or(GAP_BUCKETS.len());
+            self.histogram[bucket] += 1;
+            self.longest_gap = self.longest_gap.max(gap);
+        }
+        self.first.get_or_insert(time);
+        self.received += 1;
+        self.window.push_back(time);
+        self.trim(config, time);
+    }
+
+    // Keeps the last frame before the window, it bounds the first gap in it
+    fn trim(&mut self, config: &LinkConfig, now: std::time::Instant) {
+        while self.window.len() > 1 && self.window.get(1).is_some_and(|&second| now.saturating_duration_since(second) > config.window) {
+            self.window.pop_front();
+        }
+    }
+
+    fn count_in_window(&self, config: &LinkConfig, now: std::time::Instant) -> usize {
+        self.window.iter().filter(|&&time| now.saturating_duration_since(time) <= config.window).count()
+    }
+
+    // Time the window covers, shorter right after the first frame
+    fn span(&self, config: &LinkConfig, now: std::time::Instant) -> Option<f32> {
+        let span = now.saturating_duration_since(self.first?).min(config.window).as_secs_f32();
+        (span > 0.0).then_some(span)
+    }
+
+    fn stats(&self, config: &LinkConfig, now: std::time::Instant) -> MessageLinkStats {
+        let count = self.count_in_window(config, now);
+        let gaps: Vec<f32> = self.window.iter().zip(self.window.iter().skip(1))
+            .filter(|(_, &time)| now.saturating_duration_since(time) <= config.window)
+            .map(|(&a, &b)| b.saturating_duration_since(a).as_secs_f32())
+            .collect();
+        let jitter = if gaps.len() < 2 { 0.0 } else {
+            let mean = gaps.iter().sum::<f32>() / gaps.len() as f32;
+            (gaps.iter().map(|gap| (gap - mean).powi(2)).sum::<f32>() / gaps.len() as f32).sqrt()
+        };
+        MessageLinkStats {
+            fps: self.span(config, now).map_or(0.0, |span| count as f32 / span),
+            jitter: std::time::Duration::from_secs_f32(jitter),
+            gap_histogram: self.histogram,
+            longest_gap: self.longest_gap,
+            received: self.received,
+        }
+    }
+
+    fn loss(&self, config: &LinkConfig, rate: Option<f32>, now: std::time::Instant) -> Option<f32> {
+        let expected = rate? * self.span(config, now)?;
+        if expected < 1.0 { return None; }
+        let received = self.count_in_window(config, now) as f32;
+        Some((1.0 - received / expected).clamp(0.0, 1.0))
+    }
+}
+
+// Link metrics of one robot
+#[derive(Debug, Clone, Default)]
+pub struct RobotLink {
+    messages: [MessageTracker; 6],
+}
+
+impl RobotLink {
+    pub fn received(&mut self, config: &LinkConfig, message: LinkMessage, time: std::time::Instant) {
+        self.messages[message as usize].add(config, time);
+    }
+
+    pub fn quality(&self, config: &LinkConfig, now: std::time::Instant) -> LinkQuality {
+        let message = |message: LinkMessage| &self.messages[message as usize];
+        LinkQuality {
+            messages: LinkMessage::ALL.map(|m| message(m).stats(config, now)),
+            status_hf_loss: message(LinkMessage::StatusHF).loss(config, config.status_hf_rate, now),
+            status_lf_loss: message(LinkMessage::StatusLF).loss(config, config.status_lf_rate, now),
+        }
+    }
+}
+
+// Link metrics of every robot, shared between the Monitor and its thread
+#[derive(Debug, Default)]
+pub(crate) struct Links {
+    robots: PerRobot<RobotLink>,
+}
+
+impl Links {
+    pub fn received(&mut self, config: &LinkConfig, id: Radio_SSL_ID, message: LinkMessage, time: std::time::Instant) {
+        let Some(robot) = self.robots.get_mut(id) else { return; };
+        robot.received(config, message, time);
+    }
+
+    pub fn quality(&self, config: &LinkConfig, id: Radio_SSL_ID, now: std::time::Instant) -> Option<LinkQuality> {
+        Some(self.robots.get(id)?.quality(config, now))
+    }
+}
+
+#[cfg(test)]
+mod link_tests {
+    use super::*;
+
+    #[test]
+    fn quality() {
+        let config = LinkConfig { window: std::time::Duration::from_secs(1), status_hf_rate: Some(50.0), status_lf_rate: None };
+        let start = std::time::Instant::now();
+        let ms = |ms| start + std::time::Duration::from_millis(ms);
+        let mut link = RobotLink::default();
+
+        // Every 20 ms for 2 s, except for a 200 ms gap
+        let mut time = 0;
+        while time <= 2000 {
+            link.received(&config, LinkMessage::StatusHF, ms(time));
+            time += if time == 1400 { 200 } else { 20 };
+        }
+        let quality = link.quality(&config, ms(2000));
+        let hf = quality.get(LinkMessage::StatusHF);
+        assert!((hf.fps - 42.0).abs() < 1e-3);
+        assert_eq!(hf.longest_gap, std::time::Duration::from_millis(200));
+        assert_eq!(hf.gap_histogram[0] as u64, hf.received - 2);
+        assert_eq!(hf.gap_histogram[3], 1);
+        assert!(hf.jitter > std::time::Duration::from_millis(10));
+        let loss = quality.status_hf_loss.unwrap();
+        assert!((loss - 0.16).abs() < 1e-3);
+        assert_eq!(quality.status_lf_loss, None);
+        assert_eq!(quality.get(LinkMessage::Imu).fps, 0.0);
+
+        // Steady frames have no jitter and no loss
+        let mut steady = RobotLink::default();
+        for i in 0..=100 {
+            steady.received(&config, LinkMessage::StatusHF, ms(20 * i));
+        }
+        let quality = steady.quality(&config, ms(2000));
+        assert!(quality.get(LinkMessage::StatusHF).jitter < std::time::Duration::from_micros(10));
+        assert!(quality.status_hf_loss.unwrap() < 1e-5);
+
+        // A 50 Hz status read every 20 ms arrives in pairs or alone, gaps of up to two loops
+        let mut polled = RobotLink::default();
+        for (i, time) in [0, 0, 40, 60, 60, 100, 120].into_iter().enumerate() {
+            polled.received(&config, LinkMessage::StatusHF, ms(time) + std::time::Duration::from_micros(i as u64));
+        }
+        let histogram = polled.quality(&config, ms(120)).get(LinkMessage::StatusHF).gap_histogram;
+        assert_eq!(histogram[..3], [4, 2, 0]);
+    }
+}
@@ -0,0 +1,248 @@
+use super::field::normalize_angle;
+use super::tracker::PerRobot;
+use crate::glue::{Radio_OdometryReading, Radio_OverrideOdometry, Radio_SSL_ID};
+
+// How long after an override the odometry has to agree with it
+pub const OVERRIDE_VERIFY_WINDOW: std::time::Duration = std::time::Duration::from_millis(500);
+
+// Wheel odometry of a robot, in the frame the robot odometry was last set to
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Odometry {
+    // [m]
+    pub x: f32,
+    // [m]
+    pub y: f32,
+    // [rad]
+    pub heading: f32,
+}
+
+impl From<Radio_OdometryReading> for Odometry {
+    fn from(reading: Radio_OdometryReading) -> Self {
+        Odometry {
+            x: reading.pos_x,
+            y: reading.pos_y,
+            heading: reading.ang_z,
+        }
+    }
+}
+
+// An odometry reading as received from a robot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OdometrySample {
+    pub id: Radio_SSL_ID,
+    pub timestamp: std::time::Instant,
+    pub odometry: Odometry,
+}
+
+// Sets any combination of the odometry x, y and heading of a robot, None fields are kept
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OdometryOverride {
+    // [m]
+    pub x: Option<f32>,
+    // [m]
+    pub y: Option<f32>,
+    // [rad]
+    pub heading: Option<f32>,
+}
+
+impl OdometryOverride {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn x(mut self, x: f32) -> Self {
+        self.x = Some(x);
+        self
+    }
+
+    pub fn y(mut self, y: f32) -> Self {
+        self.y = Some(y);
+        self
+    }
+
+    pub fn position(self, x: f32, y: f32) -> Self {
+        self.x(x).y(y)
+    }
+
+    pub fn heading(mut self, heading: f32) -> Self {
+        self.heading = Some(heading);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.is_none() && self.y.is_none() && self.heading.is_none()
+    }
+
+    pub fn to_message(&self) -> Radio_OverrideOdometry {
+        Radio_OverrideOdometry {
+            pos_x: self.x.unwrap_or(0.0),
+            pos_y: self.y.unwrap_or(0.0),
+            ang_z: self.heading.unwrap_or(0.0),
+            set_pos_x: self.x.is_some(),
+            set_pos_y: self.y.is_some(),
+            set_ang_z: self.heading.is_some(),
+            _pad0: 0,
+            _pad: [0; 12],
+        }
+    }
+
+    // Whether every field that is set matches the odometry within tolerance
+    pub fn agrees_with(&self, odometry: &Odometry, tolerance: OdometryTolerance) -> bool {
+        self.x.is_none_or(|x| (odometry.x - x).abs() <= tolerance.position)
+            && self.y.is_none_or(|y| (odometry.y - y).abs() <= tolerance.position)
+            && self.heading.is_none_or(|heading| normalize_angle(odometry.heading - heading).abs() <= tolerance.heading)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OdometryTolerance {
+    // [m]
+    pub position: f32,
+    // [rad]
+    pub heading: f32,
+}
+
+impl Default for OdometryTolerance {
+    fn default() -> Self {
+        OdometryTolerance { position: 0.05, heading: 0.05 }
+    }
+}
+
+// Override the odometry from vision poses, see Monitor::set_vision_pose
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoOverride {
+    // Minimum time between overrides of a robot
+    pub interval: std::time::Duration,
+    pub position: bool,
+    pub heading: bool,
+}
+
+impl Default for AutoOverride {
+    fn default() -> Self {
+        AutoOverride { interval: std::time::Duration::from_secs(1), position: true, heading: true }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverrideOutcome {
+    Pending,
+    // A reading within OVERRIDE_VERIFY_WINDOW agreed with the override
+    Verified,
+    // No reading within OVERRIDE_VERIFY_WINDOW agreed, holds the first reading after it
+    Mismatch(Odometry),
+    // No reading was received within OVERRIDE_VERIFY_WINDOW
+    NoReadings,
+}
+
+// Sent as an event when an override is verified or found not to match
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverrideStatus {
+    pub id: Radio_SSL_ID,
+    pub sent: std::time::Instant,
+    pub target: OdometryOverride,
+    pub outcome: OverrideOutcome,
+}
+
+// The latest override of every robot, shared between the Monitor and its thread
+#[derive(Debug, Default)]
+pub(crate) struct OdometryOverrides {
+    latest: PerRobot<Option<OverrideStatus>>,
+    last_auto: PerRobot<Option<std::time::Instant>>,
+}
+
+impl OdometryOverrides {
+    pub fn sent(&mut self, id: Radio_SSL_ID, target: OdometryOverride, now: std::time::Instant) {
+        if let Some(latest) = self.latest.get_mut(id) {
+            *latest = Some(OverrideStatus { id, sent: now, target, outcome: OverrideOutcome::Pending });
+        }
+    }
+
+    // The override to send for a new vision pose, if auto override is on and one is due
+    pub fn auto_override(&mut self, auto: Option<AutoOverride>, id: Radio_SSL_ID, x: f32, y: f32, heading: f32, now: std::time::Instant) -> Option<OdometryOverride> {
+        let auto = auto?;
+        let last_auto = self.last_auto.get_mut(id)?;
+        if last_auto.is_some_and(|last| now.saturating_duration_since(last) < auto.interval) { return None; }
+
+        let mut target = OdometryOverride::new();
+        if auto.position { target = target.position(x, y); }
+        if auto.heading { target = target.heading(heading); }
+        if target.is_empty() { return None; }
+        *last_auto = Some(now);
+        Some(target)
+    }
+
+    // Check a reading against the pending override of its robot, returns the status once resolved
+    pub fn check(&mut self, tolerance: OdometryTolerance, reading: &OdometrySample) -> Option<OverrideStatus> {
+        let status = self.latest.get_mut(reading.id)?.as_mut()?;
+        if status.outcome != OverrideOutcome::Pending || reading.timestamp < status.sent { return None; }
+        if status.target.agrees_with(&reading.odometry, tolerance) {
+            status.outcome = OverrideOutcome::Verified;
+        } else if reading.timestamp.saturating_duration_since(status.sent) > OVERRIDE_VERIFY_WINDOW {
+            status.outcome = OverrideOutcome::Mismatch(reading.odometry);
+        } else {
+            return None;
+        }
+        Some(*status)
+    }
+
+    pub fn status(&mut self, id: Radio_SSL_ID, now: std::time::Instant) -> Option<OverrideStatus> {
+        let status = self.latest.get_mut(id)?.as_mut()?;
+        if status.outcome == OverrideOutcome::Pending && now.saturating_duration_since(status.sent) > OVERRIDE_VERIFY_WINDOW {
+            status.outcome = OverrideOutcome::NoReadings;
+        }
+        Some(*status)
+    }
+}
+
+#[cfg(test)]
+mod odometry_tests {
+    use super::*;
+
+    #[test]
+    fn override_message() {
+        let message = OdometryOverride::new().x(1.0).heading(0.5).to_message();
+        assert_eq!((message.pos_x, message.pos_y, message.ang_z), (1.0, 0.0, 0.5));
+        assert!(message.set_pos_x && !message.set_pos_y && message.set_ang_z);
+
+        let target = OdometryOverride::new().position(1.0, 2.0).heading(std::f32::consts::PI);
+        let tolerance = OdometryTolerance::default();
+        assert!(target.agrees_with(&Odometry { x: 1.01, y: 1.99, heading: -std::f32::consts::PI + 0.01 }, tolerance));
+        assert!(!target.agrees_with(&Odometry { x: 1.2, y: 2.0, heading: std::f32::consts::PI }, tolerance));
+        assert!(OdometryOverride::new().agrees_with(&Odometry::default(), tolerance));
+    }
+
+    #[test]
+    fn verification() {
+        let start = std::time::Instant::now();
+        let reading = |ms, x| OdometrySample { id: 1, timestamp: start + std::time::Duration::from_millis(ms), odometry: Odometry { x, y: 0.0, heading: 0.0 } };
+        let mut overrides = OdometryOverrides::default();
+
+        overrides.sent(1, OdometryOverride::new().x(3.0), start);
+        // Readings still in flight are ignored
+        assert_eq!(overrides.check(OdometryTolerance::default(), &reading(20, 0.0)), None);
+        assert_eq!(overrides.check(OdometryTolerance::default(), &reading(40, 3.0)).unwrap().outcome, OverrideOutcome::Verified);
+        assert_eq!(overrides.check(OdometryTolerance::default(), &reading(60, 0.0)), None);
+
+        overrides.sent(1, OdometryOverride::new().x(3.0), start);
+        let status = overrides.check(OdometryTolerance::default(), &reading(600, 0.0)).unwrap();
+        assert_eq!(status.outcome, OverrideOutcome::Mismatch(reading(600, 0.0).odometry));
+
+        overrides.sent(2, OdometryOverride::new().heading(1.0), start);
+        assert_eq!(overrides.status(2, start).unwrap().outcome, OverrideOutcome::Pending);
+        assert_eq!(overrides.status(2, start + OVERRIDE_VERIFY_WINDOW * 2).unwrap().outcome, OverrideOutcome::NoReadings);
+        assert_eq!(overrides.status(3, start), None);
+    }
+
+    #[test]
+    fn auto_override() {
+        let start = std::time::Instant::now();
+        let mut overrides = OdometryOverrides::default();
+        assert_eq!(overrides.auto_override(None, 0, 1.0, 2.0, 0.5, start), None);
+
+        let auto = Some(AutoOverride { heading: false, ..Default::default() });
+        assert_eq!(overrides.auto_override(auto, 0, 1.0, 2.0, 0.5, start), Some(OdometryOverride::new().position(1.0, 2.0)));
+        assert_eq!(overrides.auto_override(auto, 0, 1.0, 2.0, 0.5, start + std::time::Duration::from_millis(500)), None);
+        assert!(overrides.auto_override(auto, 0, 1.0, 2.0, 0.5, start + std::time::Duration::from_secs(1)).is_some());
+        assert!(overrides.auto_override(auto, 1, 1.0, 2.0, 0.5, start).is_some());
+    }
+}
@@ -0,0 +1,300 @@
+use super::robot::Robot;
+use super::state::StatusHF;
+use super::tracker::{LastSeen, RobotTracker};
+use crate::glue::Radio_SSL_ID;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PossessionConfig {
+    // The ball must be detected this long before possession is gained
+    pub gain_time: std::time::Duration,
+    // The ball must be missing this long before possession is lost
+    pub loss_time: std::time::Duration,
+    // ToF readings below this confidence are ignored
+    pub min_tof_confidence: u8,
+    // A ball this close in front of the ToF sensor counts as possessed [m]
+    pub tof_possession_distance: f32,
+    // Size of a cell of the ToF grid [m]
+    pub tof_cell_size: f32,
+    // Cell of the ToF grid the sensor itself is at
+    pub tof_grid_origin: (f32, f32),
+    // Position of the ToF sensor relative to the centre of the robot, x is -left/+right, y is +forward/-back [m]
+    pub tof_offset: (f32, f32),
+}
+
+impl Default for PossessionConfig {
+    fn default() -> Self {
+        PossessionConfig {
+            gain_time: std::time::Duration::from_millis(20),
+            loss_time: std::time::Duration::from_millis(100),
+            min_tof_confidence: 50,
+            tof_possession_distance: 0.02,
+            tof_cell_size: 0.01,
+            tof_grid_origin: (0.0, 0.0),
+            tof_offset: (0.0, 0.07),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PossessionState {
+    // No status received yet, or neither sensor works
+    #[default]
+    Unknown,
+    Free,
+    Possessed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BallSensor {
+    Breakbeam,
+    Tof,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PossessionEstimate {
+    pub state: PossessionState,
+    // When the state was entered
+    pub since: Option<std::time::Instant>,
+    pub breakbeam_ok: bool,
+    pub tof_ok: bool,
+    // Ball relative to the centre of the robot from the ToF sensor, x is -left/+right, y is
+    // +forward/-back [m]. None if the ToF sensor doesn't see the ball confidently.
+    pub ball_position: Option<(f32, f32)>,
+}
+
+impl PossessionEstimate {
+    pub fn has_ball(&self) -> bool {
+        self.state == PossessionState::Possessed
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PossessionChange {
+    Gained,
+    Lost,
+    SensorFault(BallSensor),
+    SensorRecovered(BallSensor),
+}
+
+// Sent as an event when a robot gains or loses the ball, or a ball sensor fails or recovers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PossessionEvent {
+    pub id: Radio_SSL_ID,
+    pub change: PossessionChange,
+    pub estimate: PossessionEstimate,
+}
+
+// The ball sensor readings of one PrimaryStatusHF
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BallReading {
+    pub breakbeam_ok: bool,
+    pub breakbeam_detected: bool,
+    pub tof_ok: bool,
+    // Cell of the ToF grid, None if it doesn't detect a ball
+    pub tof_xy: Option<(i8, u8)>,
+    pub tof_confidence: u8,
+}
+
+impl From<&StatusHF> for BallReading {
+    fn from(status: &StatusHF) -> Self {
+        BallReading {
+            breakbeam_ok: status.breakbeam_sensor_ok,
+            breakbeam_detected: status.breakbeam_ball_detected,
+            tof_ok: status.tof_sensor_ok,
+            tof_xy: status.tof_xy,
+            tof_confidence: status.tof_confidence,
+        }
+    }
+}
+
+// Debounced ball possession of one robot, fused from the breakbeam and ToF sensor
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BallPossession {
+    estimate: PossessionEstimate,
+    // Detection differing from the state, and since when
+    pending: Option<(bool, std::time::Instant)>,
+    seen: bool,
+}
+
+impl PossessionConfig {
+    // Position of the ball relative to the centre of the robot from its ToF grid cell [m]
+    pub fn tof_position(&self, (x, y): (i8, u8)) -> (f32, f32) {
+        (
+            (x as f32 - self.tof_grid_origin.0) * self.tof_cell_size + self.tof_offset.0,
+            (y as f32 - self.tof_grid_origin.1) * self.tof_cell_size + self.tof_offset.1,
+        )
+    }
+}
+
+impl BallPossession {
+    // Whether the sensors that work detect the ball, None if neither works
+    fn detect(config: &PossessionConfig, reading: &BallReading, ball_position: Option<(f32, f32)>) -> Option<bool> {
+        let tof_close = ball_position.is_some_and(|(_, y)| y - config.tof_offset.1 <= config.tof_possession_distance);
+        match (reading.breakbeam_ok, reading.tof_ok) {
+            (true, true) => Some(reading.breakbeam_detected || tof_close),
+            (true, false) => Some(reading.breakbeam_detected),
+            (false, true) => Some(tof_close),
+            (false, false) => None,
+        }
+    }
+
+    // Add the readings of a received PrimaryStatusHF, returns what changed
+    pub fn update(&mut self, config: &PossessionConfig, reading: &BallReading, time: std::time::Instant) -> Vec<PossessionChange> {
+        let mut changes = vec![];
+        let sensors = [
+            (BallSensor::Breakbeam, self.estimate.breakbeam_ok, reading.breakbeam_ok),
+            (BallSensor::Tof, self.estimate.tof_ok, reading.tof_ok),
+        ];
+        for (sensor, was_ok, ok) in sensors {
+            if !self.seen && ok { continue; }
+            if was_ok && !ok { changes.push(PossessionChange::SensorFault(sensor)); }
+            if !was_ok && ok { changes.push(PossessionChange::SensorRecovered(sensor)); }
+        }
+        self.seen = true;
+        self.estimate.breakbeam_ok = reading.breakbeam_ok;
+        self.estimate.tof_ok = reading.tof_ok;
+        self.estimate.ball_position = reading.tof_xy
+            .filter(|_| reading.tof_ok && reading.tof_confidence >= config.min_tof_confidence)
+            .map(|xy| config.tof_position(xy));
+
+        let Some(detected) = Self::detect(config, reading, self.estimate.ball_position) else {
+            self.pending = None;
+            self.set_state(PossessionState::Unknown, time);
+            return changes;
+        };
+        if detected == (self.estimate.state == PossessionState::Possessed) && self.estimate.state != PossessionState::Unknown {
+            self.pending = None;
+            return changes;
+        }
+
+        let since = match self.pending {
+            Some((pending, since)) if pending == detected => since,
+            _ => time,
+        };
+        self.pending = Some((detected, since));
+        // Nothing to debounce when the state was unknown and there is no ball
+        let hold = match (detected, self.estimate.state) {
+            (true, _) => config.gain_time,
+            (false, PossessionState::Unknown) => std::time::Duration::ZERO,
+            (false, _) => config.loss_time,
+        };
+        if time.saturating_duration_since(since) >= hold {
+            self.pending = None;
+            let was_possessed = self.estimate.has_ball();
+            self.set_state(if detected { PossessionState::Possessed } else { PossessionState::Free }, time);
+            if detected {
+                changes.push(PossessionChange::Gained);
+            } else if was_possessed {
+                changes.push(PossessionChange::Lost);
+            }
+        }
+        changes
+    }
+
+    fn set_state(&mut self, state: PossessionState, time: std::time::Instant) {
+        if self.estimate.state != state {
+            self.estimate.state = state;
+            self.estimate.since = Some(time);
+        }
+    }
+
+    pub fn estimate(&self) -> PossessionEstimate {
+        self.estimate
+    }
+}
+
+// Ball possession of a robot, kept by the monitor thread
+#[derive(Debug, Default)]
+pub(crate) struct PossessionTracker {
+    possession: BallPossession,
+    last_status: LastSeen,
+}
+
+impl PossessionTracker {
+    pub fn estimate(&self) -> PossessionEstimate {
+        self.possession.estimate()
+    }
+}
+
+impl RobotTracker for PossessionTracker {
+    type Config = PossessionConfig;
+    type Event = PossessionEvent;
+
+    // Add a new PrimaryStatusHF, returns what changed
+    fn update(&mut self, config: &PossessionConfig, id: Radio_SSL_ID, robot: &mut Robot) -> Vec<PossessionEvent> {
+        let Some(status) = robot.state().status_hf else { return vec![]; };
+        let Some(received) = self.last_status.take_new(robot.status_hf_timestamp()) else { return vec![]; };
+        let changes = self.possession.update(config, &(&status).into(), received);
+        changes.into_iter().map(|change| PossessionEvent { id, change, estimate: self.possession.estimate() }).collect()
+    }
+}
+
+#[cfg(test)]
+mod possession_tests {
+    use super::*;
+
+    // None for a broken breakbeam
+    fn reading(breakbeam: Option<bool>, tof: Option<(i8, u8)>) -> BallReading {
+        BallReading {
+            breakbeam_ok: breakbeam.is_some(),
+            breakbeam_detected: breakbeam == Some(true),
+            tof_ok: true,
+            tof_xy: tof,
+            tof_confidence: 100,
+        }
+    }
+
+    #[test]
+    fn debounce() {
+        let config = PossessionConfig::default();
+        let start = std::time::Instant::now();
+        let ms = |ms| start + std::time::Duration::from_millis(ms);
+        let mut possession = BallPossession::default();
+
+        assert!(possession.update(&config, &reading(Some(false), None), ms(0)).is_empty());
+        assert_eq!(possession.estimate().state, PossessionState::Free);
+
+        // A short blip is ignored
+        possession.update(&config, &reading(Some(true), None), ms(10));
+        possession.update(&config, &reading(Some(false), None), ms(20));
+        possession.update(&config, &reading(Some(true), None), ms(30));
+        assert!(!possession.estimate().has_ball());
+        assert_eq!(possession.update(&config, &reading(Some(true), None), ms(50)), vec![PossessionChange::Gained]);
+        assert_eq!(possession.estimate().since, Some(ms(50)));
+
+        // The ball is kept through a short dropout
+        possession.update(&config, &reading(Some(false), None), ms(60));
+        possession.update(&config, &reading(Some(true), None), ms(100));
+        assert!(possession.update(&config, &reading(Some(false), None), ms(120)).is_empty());
+        assert_eq!(possession.update(&config, &reading(Some(false), None), ms(220)), vec![PossessionChange::Lost]);
+    }
+
+    #[test]
+    fn fusion() {
+        let config = PossessionConfig::default();
+        let start = std::time::Instant::now();
+        let ms = |ms| start + std::time::Duration::from_millis(ms);
+        let mut possession = BallPossession::default();
+
+        // A broken breakbeam falls back to ToF, a ball far in front isn't possessed
+        assert_eq!(possession.update(&config, &reading(Some(false), None), ms(0)), vec![]);
+        assert_eq!(possession.update(&config, &reading(None, Some((1, 20))), ms(10)), vec![PossessionChange::SensorFault(BallSensor::Breakbeam)]);
+        let estimate = possession.estimate();
+        assert_eq!(estimate.state, PossessionState::Free);
+        let (x, y) = estimate.ball_position.unwrap();
+        assert!((x - 0.01).abs() < 1e-6 && (y - 0.27).abs() < 1e-6);
+
+        possession.update(&config, &reading(None, Some((0, 1))), ms(20));
+        assert_eq!(possession.update(&config, &reading(None, Some((0, 1))), ms(40)), vec![PossessionChange::Gained]);
+
+        // Without working sensors the possession is unknown
+        let mut broken = reading(None, None);
+        broken.tof_ok = false;
+        assert_eq!(possession.update(&config, &broken, ms(50)), vec![PossessionChange::SensorFault(BallSensor::Tof)]);
+        assert_eq!(possession.estimate().state, PossessionState::Unknown);
+        assert_eq!(possession.update(&config, &reading(Some(true), None), ms(60)),
+            vec![PossessionChange::SensorRecovered(BallSensor::Breakbeam), PossessionChange::SensorRecovered(BallSensor::Tof)]);
+        assert_eq!(possession.update(&config, &reading(Some(true), None), ms(80)), vec![PossessionChange::Gained]);
+        assert_eq!(possession.estimate().state, PossessionState::Possessed);
+    }
+}
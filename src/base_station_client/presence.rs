@@ -0,0 +1,282 @@
+use super::basestation::MAX_NUM_ROBOTS;
+use super::robot::Robot;
+use super::tracker::PerRobot;
+use crate::glue::Radio_SSL_ID;
+
+// Kinds of messages a robot sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageClass {
+    StatusHF,
+    StatusLF,
+    Imu,
+    Odometry,
+}
+
+impl MessageClass {
+    pub const ALL: [MessageClass; 4] = [Self::StatusHF, Self::StatusLF, Self::Imu, Self::Odometry];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    // Without a message for this long the robot is stale
+    pub stale: std::time::Duration,
+    // Without a message for this long the robot is offline
+    pub offline: std::time::Duration,
+}
+
+impl Timeouts {
+    pub fn new(stale: std::time::Duration, offline: std::time::Duration) -> Self {
+        Timeouts { stale, offline }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresenceConfig {
+    // Timeouts per message class, None if the class doesn't count towards presence.
+    // A robot is as present as its most recent message allows.
+    pub status_hf: Option<Timeouts>,
+    pub status_lf: Option<Timeouts>,
+    pub imu: Option<Timeouts>,
+    pub odometry: Option<Timeouts>,
+    // How long a robot stays Rejoined after it came back from Offline
+    pub rejoined: std::time::Duration,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        // Offline after 400 ms
+        let timeouts = Some(Timeouts::new(std::time::Duration::from_millis(200), std::time::Duration::from_millis(400)));
+        PresenceConfig {
+            status_hf: timeouts,
+            status_lf: timeouts,
+            imu: timeouts,
+            odometry: timeouts,
+            rejoined: std::time::Duration::from_secs(2),
+        }
+    }
+}
+
+impl PresenceConfig {
+    pub fn timeouts(&self, class: MessageClass) -> Option<Timeouts> {
+        match class {
+            MessageClass::StatusHF => self.status_hf,
+            MessageClass::StatusLF => self.status_lf,
+            MessageClass::Imu => self.imu,
+            MessageClass::Odometry => self.odometry,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PresenceState {
+    #[default]
+    NeverSeen,
+    Online,
+    // Messages are late, but the robot isn't offline yet
+    Stale,
+    Offline,
+    // Back from Offline, becomes Online after PresenceConfig::rejoined
+    Rejoined,
+}
+
+impl PresenceState {
+    pub fn is_present(&self) -> bool {
+        matches!(self, Self::Online | Self::Stale | Self::Rejoined)
+    }
+}
+
+// Sent as an event when the presence state of a robot changes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PresenceEvent {
+    pub id: Radio_SSL_ID,
+    pub from: PresenceState,
+    pub to: PresenceState,
+    // When the change happened, e.g. when the last message timed out
+    pub time: std::time::Instant,
+    pub presence: Presence,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Presence {
+    pub state: PresenceState,
+    // When the state was entered
+    pub since: Option<std::time::Instant>,
+    // When the robot last came online, None while offline
+    pub online_since: Option<std::time::Instant>,
+    // Most recent message of a class that counts towards presence
+    pub last_seen: Option<std::time::Instant>,
+    // Times the robot went offline
+    pub dropouts: u32,
+    // How long the robot was offline the last time
+    pub last_dropout: Option<std::time::Duration>,
+    // Total time offline, not counting the current dropout
+    pub offline_time: std::time::Duration,
+}
+
+impl Presence {
+    // Time since the robot last came online, None while offline
+    pub fn uptime(&self, now: std::time::Instant) -> Option<std::time::Duration> {
+        self.online_since.map(|since| now.saturating_duration_since(since))
+    }
+
+    // Time since the robot went offline, None while it's not
+    pub fn offline_for(&self, now: std::time::Instant) -> Option<std::time::Duration> {
+        if self.state != PresenceState::Offline { return None; }
+        self.since.map(|since| now.saturating_duration_since(since))
+    }
+
+    // What the received messages alone say, and since when
+    pub(crate) fn evaluate(
+        config: &PresenceConfig,
+        received: &[Option<std::time::Instant>; 4],
+        now: std::time::Instant,
+    ) -> Option<(PresenceState, std::time::Instant)> {
+        let classes: Vec<(std::time::Instant, Timeouts)> = MessageClass::ALL.iter().zip(received)
+            .filter_map(|(&class, &received)| Some((received?, config.timeouts(class)?)))
+            .collect();
+        let latest = |timeout: fn(&Timeouts) -> std::time::Duration| {
+            classes.iter().map(|(received, timeouts)| *received + timeout(timeouts)).max()
+        };
+        let stale_at = latest(|timeouts| timeouts.stale)?;
+        let offline_at = latest(|timeouts| timeouts.offline)?;
+        Some(if now < stale_at {
+            (PresenceState::Online, classes.iter().map(|(received, _)| *received).max()?)
+        } else if now < offline_at {
+            (PresenceState::Stale, stale_at)
+        } else {
+            (PresenceState::Offline, offline_at)
+        })
+    }
+
+    // received holds the time of the latest message of every MessageClass, in the order of
+    // MessageClass::ALL. Returns the change of state, if any.
+    pub fn update(
+        &mut self,
+        config: &PresenceConfig,
+        received: &[Option<std::time::Instant>; 4],
+        now: std::time::Instant,
+    ) -> Option<(PresenceState, PresenceState, std::time::Instant)> {
+        let (observed, time) = Self::evaluate(config, received, now)?;
+        self.last_seen = MessageClass::ALL.iter().zip(received)
+            .filter(|(&class, _)| config.timeouts(class).is_some())
+            .filter_map(|(_, &received)| received)
+            .max();
+
+        let from = self.state;
+        let to = match (from, observed) {
+            (PresenceState::Offline, PresenceState::Offline) => return None,
+            (PresenceState::Offline, _) => PresenceState::Rejoined,
+            (PresenceState::Rejoined, PresenceState::Online | PresenceState::Stale) => {
+                let rejoined = self.since.is_some_and(|since| now.saturating_duration_since(since) >= config.rejoined);
+                if !rejoined { return None; }
+                observed
+            }
+            (_, observed) => observed,
+        };
+        if to == from { return None; }
+
+        let time = match (from, to) {
+            // Coming back from offline happened with the message that ended it
+            (_, PresenceState::Rejoined) => self.last_seen.unwrap_or(time),
+            (PresenceState::Rejoined, _) => self.since.map_or(time, |since| since + config.rejoined),
+            _ => time,
+        };
+        match to {
+            PresenceState::Offline => {
+                if from.is_present() { self.dropouts += 1; }
+                self.online_since = None;
+            }
+            PresenceState::Rejoined => {
+                let dropout = self.since.map_or(std::time::Duration::ZERO, |since| time.saturating_duration_since(since));
+                self.last_dropout = Some(dropout);
+                self.offline_time += dropout;
+                self.online_since = Some(time);
+            }
+            PresenceState::Online | PresenceState::Stale if self.online_since.is_none() => self.online_since = Some(time),
+            _ => (),
+        }
+        self.state = to;
+        self.since = Some(time);
+        Some((from, to, time))
+    }
+}
+
+// Presence of every robot, shared between the Monitor and its thread
+#[derive(Debug, Default)]
+pub(crate) struct Presences {
+    robots: PerRobot<Presence>,
+}
+
+impl Presences {
+    // Update the presence of every robot and store it on the robots, Robot::is_online follows it
+    pub fn update(&mut self, config: &PresenceConfig, robots: &mut [Robot; MAX_NUM_ROBOTS], now: std::time::Instant) -> Vec<PresenceEvent> {
+        let mut events = vec![];
+        for ((id, presence), robot) in self.robots.iter_mut().zip(robots.iter_mut()) {
+            let received = MessageClass::ALL.map(|class| robot.last_received(class));
+            if let Some((from, to, time)) = presence.update(config, &received, now) {
+                events.push(PresenceEvent { id, from, to, time, presence: *presence });
+            }
+            robot.set_presence(*presence);
+        }
+        events
+    }
+
+    pub fn presence(&self, id: Radio_SSL_ID) -> Option<Presence> {
+        self.robots.get(id).copied()
+    }
+}
+
+#[cfg(test)]
+mod presence_tests {
+    use super::*;
+
+    #[test]
+    fn transitions() {
+        let config = PresenceConfig { odometry: None, ..Default::default() };
+        let start = std::time::Instant::now();
+        let ms = |ms| start + std::time::Duration::from_millis(ms);
+        let mut presence = Presence::default();
+        let mut update = |hf: u64, odometry: u64, now: u64| {
+            presence.update(&config, &[Some(ms(hf)), None, None, Some(ms(odometry))], ms(now)).map(|(_, to, time)| (to, time))
+        };
+
+        assert_eq!(update(0, 0, 10), Some((PresenceState::Online, ms(0))));
+        assert_eq!(update(100, 0, 150), None);
+        assert_eq!(update(100, 0, 350), Some((PresenceState::Stale, ms(300))));
+        // Odometry doesn't count
+        assert_eq!(update(100, 600, 600), Some((PresenceState::Offline, ms(500))));
+        assert_eq!(update(100, 600, 900), None);
+
+        assert_eq!(update(1000, 1000, 1010), Some((PresenceState::Rejoined, ms(1000))));
+        assert_eq!(update(2900, 2900, 2950), None);
+        assert_eq!(update(3000, 3000, 3010), Some((PresenceState::Online, ms(3000))));
+
+        let presence = presence;
+        assert_eq!(presence.dropouts, 1);
+        assert_eq!(presence.last_dropout, Some(std::time::Duration::from_millis(500)));
+        assert_eq!(presence.uptime(ms(4000)), Some(std::time::Duration::from_millis(3000)));
+        assert_eq!(presence.offline_for(ms(4000)), None);
+        assert_eq!(Presence::default().update(&config, &[None; 4], ms(0)), None);
+    }
+
+    #[test]
+    fn robots_follow_presence() {
+        let start = std::time::Instant::now();
+        let mut robots: [Robot; MAX_NUM_ROBOTS] = Default::default();
+        let status_hf: crate::glue::Radio_PrimaryStatusHF = unsafe { std::mem::zeroed() };
+        robots[1].update_status_hf(status_hf);
+        // Without a Monitor the default timeouts apply
+        assert!(robots[1].is_online() && !robots[0].is_online());
+
+        let mut presences = Presences::default();
+        assert_eq!(presences.update(&PresenceConfig::default(), &mut robots, start).len(), 1);
+        assert!(robots[1].is_online() && robots[1].state().online);
+        assert_eq!(robots[1].presence().unwrap().state, PresenceState::Online);
+
+        // A shorter timeout takes the robot offline although it was online by the defaults
+        let timeouts = Some(Timeouts::new(std::time::Duration::ZERO, std::time::Duration::ZERO));
+        let config = PresenceConfig { status_hf: timeouts, ..Default::default() };
+        presences.update(&config, &mut robots, start + std::time::Duration::from_millis(100));
+        assert!(!robots[1].is_online() && !robots[1].state().online);
+    }
+}
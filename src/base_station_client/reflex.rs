@@ -0,0 +1,314 @@
+use super::basestation::MAX_NUM_ROBOTS;
+use super::robot::Robot;
+use super::scaling;
+use super::smart_kick::KickParams;
+use crate::glue::{HG_ReflexState, Radio_GenericCommand, Radio_RobotCommand, Radio_SSL_ID};
+
+// Number of transitions kept per robot
+pub const REFLEX_HISTORY_LEN: usize = 32;
+
+// What a robot reports in HG_ReflexState
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportedReflex {
+    Disarmed,
+    WaitingForBall,
+    Kicked,
+}
+
+impl ReportedReflex {
+    // Every variant is matched, so a new state in the firmware header has to be handled here
+    pub fn of(state: HG_ReflexState) -> Self {
+        match state {
+            HG_ReflexState::DISARMED => ReportedReflex::Disarmed,
+            HG_ReflexState::WAITING_FOR_BALL => ReportedReflex::WaitingForBall,
+            HG_ReflexState::KICKED => ReportedReflex::Kicked,
+        }
+    }
+}
+
+// Reflex kick phase of a robot as tracked by glue, from what was sent, the reported
+// HG_ReflexState and the kick counter
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReflexPhase {
+    #[default]
+    Disarmed,
+    // The arm command is being sent, the robot doesn't report waiting for the ball yet
+    Armed,
+    // The robot reports it is armed and waiting for the ball
+    WaitingForBall,
+    // The robot reports a kick or the kick counter went up while armed, the arm command is no
+    // longer sent
+    Kicked,
+    // The disarm command is being sent, the robot doesn't report being disarmed yet
+    Disarming,
+    // The robot reports being disarmed after the disarm command went out, or while glue still
+    // arms it
+    Aborted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReflexTransition {
+    pub at: std::time::Instant,
+    pub from: ReflexPhase,
+    pub to: ReflexPhase,
+}
+
+// Sent as an event whenever the kick counter of a robot goes up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReflexKickEvent {
+    pub id: Radio_SSL_ID,
+    pub counter: u8,
+    // Time between arming and receiving the status with the new counter, None if not armed
+    pub latency: Option<std::time::Duration>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReflexKickStatus {
+    pub phase: ReflexPhase,
+    pub since: Option<std::time::Instant>,
+    pub state: Option<ReportedReflex>,
+    pub counter: Option<u8>,
+    pub last_latency: Option<std::time::Duration>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct RobotReflex {
+    status: ReflexKickStatus,
+    armed_at: Option<std::time::Instant>,
+    kick_time_i: u8,
+    // Arm or disarm command put into outgoing commands
+    robot_command: Option<Radio_RobotCommand>,
+    // When the first command carrying the disarm command went out
+    disarm_sent: Option<std::time::Instant>,
+    last_received: Option<std::time::Instant>,
+    transitions: std::collections::VecDeque<ReflexTransition>,
+}
+
+impl RobotReflex {
+    fn transition(&mut self, to: ReflexPhase, at: std::time::Instant) {
+        if self.status.phase == to { return; }
+        if self.transitions.len() >= REFLEX_HISTORY_LEN {
+            self.transitions.pop_front();
+        }
+        self.transitions.push_back(ReflexTransition { at, from: self.status.phase, to });
+        self.status.phase = to;
+        self.status.since = Some(at);
+        if !matches!(to, ReflexPhase::Armed | ReflexPhase::WaitingForBall | ReflexPhase::Disarming) {
+            self.robot_command = None;
+        }
+        if to != ReflexPhase::Disarming {
+            self.disarm_sent = None;
+        }
+    }
+
+    fn is_armed(&self) -> bool {
+        matches!(self.status.phase, ReflexPhase::Armed | ReflexPhase::WaitingForBall)
+    }
+}
+
+// Reflex kick tracking of every robot, shared between the Monitor and its thread
+#[derive(Debug, Default)]
+pub(crate) struct ReflexKicks {
+    robots: [RobotReflex; MAX_NUM_ROBOTS],
+}
+
+impl ReflexKicks {
+    // params.robot_command is the Radio_RobotCommand that arms reflex mode on the robot
+    pub fn arm(&mut self, id: Radio_SSL_ID, params: KickParams, now: std::time::Instant) -> Result<(), ()> {
+        let robot = self.robots.get_mut(id as usize).ok_or(())?;
+        robot.kick_time_i = scaling::KICK_TIME.encode(params.kick_time).raw;
+        robot.robot_command = Some(params.robot_command);
+        if !robot.is_armed() {
+            robot.armed_at = Some(now);
+            robot.transition(ReflexPhase::Armed, now);
+        }
+        Ok(())
+    }
+
+    // robot_command is the Radio_RobotCommand that disarms reflex mode on the robot. It is sent
+    // until the robot reports being disarmed.
+    pub fn disarm(&mut self, id: Radio_SSL_ID, robot_command: Radio_RobotCommand, now: std::time::Instant) -> Result<(), ()> {
+        let robot = self.robots.get_mut(id as usize).ok_or(())?;
+        if robot.is_armed() || robot.status.phase == ReflexPhase::Disarming {
+            robot.robot_command = Some(robot_command);
+            robot.transition(ReflexPhase::Disarming, now);
+        }
+        Ok(())
+    }
+
+    // Put the arm command into an outgoing command while armed, or the disarm command while
+    // disarming
+    pub fn apply(&self, id: Radio_SSL_ID, gen_command: &mut Radio_GenericCommand) {
+        let Some(robot) = self.robots.get(id as usize) else { return; };
+        if let Some(robot_command) = robot.robot_command {
+            if robot.status.phase != ReflexPhase::Disarming {
+                gen_command.kick_time_i = robot.kick_time_i;
+            }
+            gen_command.robot_command = robot_command;
+        }
+    }
+
+    // A command prepared by apply() was queued. Once the disarm command went out, the robot
+    // reporting being disarmed ends the reflex kick.
+    pub fn sent(&mut self, id: Radio_SSL_ID, gen_command: &Radio_GenericCommand, now: std::time::Instant) {
+        let Some(robot) = self.robots.get_mut(id as usize) else { return; };
+        if robot.status.phase == ReflexPhase::Disarming && robot.disarm_sent.is_none() && robot.robot_command == Some(gen_command.robot_command) {
+            robot.disarm_sent = Some(now);
+        }
+    }
+
+    // Process a status of a robot, every status is only processed once. state is the reported
+    // HG_ReflexState, see ReportedReflex::of.
+    pub fn update(&mut self, id: Radio_SSL_ID, state: ReportedReflex, counter: u8, received: std::time::Instant) -> Option<ReflexKickEvent> {
+        let robot = self.robots.get_mut(id as usize)?;
+        if robot.last_received.is_some_and(|last| received <= last) { return None; }
+        robot.last_received = Some(received);
+
+        let previous_counter = robot.status.counter.replace(counter);
+        let previous_state = robot.status.state.replace(state);
+        let armed_at = robot.armed_at.filter(|&armed_at| robot.is_armed() && received >= armed_at);
+
+        if previous_counter.is_some_and(|previous| previous != counter) {
+            let latency = armed_at.map(|armed_at| received.saturating_duration_since(armed_at));
+            if latency.is_some() {
+                robot.status.last_latency = latency;
+                robot.transition(ReflexPhase::Kicked, received);
+            }
+            return Some(ReflexKickEvent { id, counter, latency });
+        }
+
+        if robot.status.phase == ReflexPhase::Disarming && state == ReportedReflex::Disarmed
+            && robot.disarm_sent.is_some_and(|sent| received >= sent) {
+            robot.transition(ReflexPhase::Aborted, received);
+        }
+        if let Some(armed_at) = armed_at {
+            match (robot.status.phase, state) {
+                (ReflexPhase::Armed | ReflexPhase::WaitingForBall, ReportedReflex::Kicked) if previous_state != Some(state) => {
+                    robot.status.last_latency = Some(received.saturating_duration_since(armed_at));
+                    robot.transition(ReflexPhase::Kicked, received);
+                }
+                (ReflexPhase::Armed, ReportedReflex::WaitingForBall) => robot.transition(ReflexPhase::WaitingForBall, received),
+                (ReflexPhase::WaitingForBall, ReportedReflex::Disarmed) => robot.transition(ReflexPhase::Aborted, received),
+                _ => (),
+            }
+        }
+        None
+    }
+
+    pub fn update_from_robots(&mut self, robots: &[Robot; MAX_NUM_ROBOTS]) -> Vec<ReflexKickEvent> {
+        let mut events = vec![];
+        for (id, robot) in robots.iter().enumerate() {
+            let (Some(state), Some(counter), Some(received)) =
+                (robot.reflex_kick_state(), robot.reflex_kick_counter(), robot.status_hf_timestamp()) else { continue; };
+            events.extend(self.update(id as Radio_SSL_ID, ReportedReflex::of(state), counter, received));
+        }
+        events
+    }
+
+    pub fn status(&self, id: Radio_SSL_ID) -> Option<ReflexKickStatus> {
+        self.robots.get(id as usize).map(|robot| robot.status)
+    }
+
+    pub fn transitions(&self, id: Radio_SSL_ID) -> Vec<ReflexTransition> {
+        self.robots.get(id as usize).map_or(vec![], |robot| robot.transitions.iter().copied().collect())
+    }
+}
+
+#[cfg(test)]
+mod reflex_tests {
+    use super::*;
+    use crate::base_station_client::command::RobotCommand;
+
+    #[test]
+    fn arm_kick() {
+        let start = std::time::Instant::now();
+        let at = |ms| start + std::time::Duration::from_millis(ms);
+        let mut reflex = ReflexKicks::default();
+        let params = KickParams::new(scaling::KICK_TIME.decode(4), Radio_RobotCommand::NONE);
+
+        assert_eq!(reflex.update(0, ReportedReflex::Disarmed, 3, at(0)), None);
+        reflex.arm(0, params, at(10)).unwrap();
+        let mut gen_command = RobotCommand::new().generic_command();
+        reflex.apply(0, &mut gen_command);
+        assert_eq!(gen_command.kick_time_i, 4);
+
+        // The same status again is ignored
+        assert_eq!(reflex.update(0, ReportedReflex::Disarmed, 3, at(0)), None);
+        reflex.update(0, ReportedReflex::WaitingForBall, 3, at(20));
+        assert_eq!(reflex.status(0).unwrap().phase, ReflexPhase::WaitingForBall);
+
+        let event = reflex.update(0, ReportedReflex::Disarmed, 4, at(110)).unwrap();
+        assert_eq!(event.latency, Some(std::time::Duration::from_millis(100)));
+        let status = reflex.status(0).unwrap();
+        assert_eq!(status.phase, ReflexPhase::Kicked);
+        assert_eq!(status.since, Some(at(110)));
+
+        let mut gen_command = RobotCommand::new().generic_command();
+        reflex.apply(0, &mut gen_command);
+        assert_eq!(gen_command.kick_time_i, 0);
+
+        let phases: Vec<ReflexPhase> = reflex.transitions(0).iter().map(|t| t.to).collect();
+        assert_eq!(phases, [ReflexPhase::Armed, ReflexPhase::WaitingForBall, ReflexPhase::Kicked]);
+
+        // Kicks while not armed are still reported
+        assert_eq!(reflex.update(0, ReportedReflex::Disarmed, 5, at(200)).unwrap().latency, None);
+    }
+
+    #[test]
+    fn reported_kick() {
+        let start = std::time::Instant::now();
+        let at = |ms| start + std::time::Duration::from_millis(ms);
+        let mut reflex = ReflexKicks::default();
+        let params = KickParams::new(0.0, Radio_RobotCommand::NONE);
+
+        // A robot still reporting its previous kick isn't taken as kicking again
+        reflex.update(2, ReportedReflex::Kicked, 0, at(0));
+        reflex.arm(2, params, at(0)).unwrap();
+        reflex.update(2, ReportedReflex::Kicked, 0, at(10));
+        assert_eq!(reflex.status(2).unwrap().phase, ReflexPhase::Armed);
+
+        reflex.update(2, ReportedReflex::WaitingForBall, 0, at(30));
+        reflex.update(2, ReportedReflex::Kicked, 0, at(50));
+        let status = reflex.status(2).unwrap();
+        assert_eq!(status.phase, ReflexPhase::Kicked);
+        assert_eq!(status.last_latency, Some(std::time::Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn abort() {
+        let start = std::time::Instant::now();
+        let at = |ms| start + std::time::Duration::from_millis(ms);
+        let mut reflex = ReflexKicks::default();
+        let params = KickParams::new(0.0, Radio_RobotCommand::NONE);
+        assert!(reflex.arm(MAX_NUM_ROBOTS as u8, params, at(0)).is_err());
+
+        reflex.update(1, ReportedReflex::Disarmed, 0, at(0));
+        reflex.arm(1, params, at(0)).unwrap();
+        reflex.update(1, ReportedReflex::WaitingForBall, 0, at(10));
+        reflex.update(1, ReportedReflex::Disarmed, 0, at(20));
+        assert_eq!(reflex.status(1).unwrap().phase, ReflexPhase::Aborted);
+
+        // Disarming lasts until the robot reports being disarmed after the disarm command went out
+        reflex.arm(1, params, at(30)).unwrap();
+        reflex.disarm(1, Radio_RobotCommand::NONE, at(40)).unwrap();
+        assert_eq!(reflex.status(1).unwrap().phase, ReflexPhase::Disarming);
+        let mut gen_command = RobotCommand::new().generic_command();
+        reflex.apply(1, &mut gen_command);
+        assert_eq!(gen_command.robot_command, Radio_RobotCommand::NONE);
+        reflex.update(1, ReportedReflex::Disarmed, 0, at(45));
+        assert_eq!(reflex.status(1).unwrap().phase, ReflexPhase::Disarming);
+        reflex.sent(1, &gen_command, at(50));
+        reflex.update(1, ReportedReflex::WaitingForBall, 0, at(60));
+        assert_eq!(reflex.status(1).unwrap().phase, ReflexPhase::Disarming);
+        reflex.update(1, ReportedReflex::Disarmed, 0, at(70));
+        let status = reflex.status(1).unwrap();
+        assert_eq!((status.phase, status.since), (ReflexPhase::Aborted, Some(at(70))));
+        assert_eq!(reflex.transitions(1).len(), 6);
+
+        // Nothing is sent any more
+        let mut gen_command = RobotCommand::new().generic_command();
+        gen_command.kick_time_i = 9;
+        reflex.apply(1, &mut gen_command);
+        assert_eq!(gen_command.kick_time_i, 9);
+    }
+}
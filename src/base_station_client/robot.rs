@@ -1,13 +1,25 @@
 use crate::glue::{self, Radio_ImuReadings};
 
+use super::command::{GlobalCommand, RobotCommand};
+use super::diagnostics::MotorVerdict;
+use super::imu::Orientation;
+use super::kinematics::Kinematics;
+use super::odometry::Odometry;
+use super::presence::{MessageClass, Presence, PresenceConfig};
+use super::scaling;
+use super::state::*;
 use super::utils::Stamped;
 #[derive(Debug, Clone, Copy)]
 pub struct Robot {
     status_hf: Stamped<crate::glue::Radio_PrimaryStatusHF>,
     status_lf: Stamped<crate::glue::Radio_PrimaryStatusLF>,
     imu_reading: Stamped<crate::glue::Radio_ImuReadings>,
+    odometry: Stamped<crate::glue::Radio_OdometryReading>,
     command: Stamped<crate::glue::Radio_Command>,
     global_command: Stamped<crate::glue::Radio_GlobalCommand>,
+    motor_verdicts: [MotorVerdict; 5],
+    orientation: Option<Orientation>,
+    presence: Option<Presence>,
 }
 
 impl Default for Robot {
@@ -16,8 +28,12 @@ impl Default for Robot {
             status_hf: Stamped::NothingYet,
             status_lf: Stamped::NothingYet,
             imu_reading: Stamped::NothingYet,
+            odometry: Stamped::NothingYet,
             command: Stamped::NothingYet,
             global_command: Stamped::NothingYet,
+            motor_verdicts: [MotorVerdict::Unknown; 5],
+            orientation: None,
+            presence: None,
         }
     }
 }
@@ -32,6 +48,9 @@ impl Robot {
     pub fn update_imu_reading(&mut self, imu_reading : crate::glue::Radio_ImuReadings) {
         self.imu_reading.update(imu_reading);
     }
+    pub fn update_odo_reading(&mut self, odo_reading : crate::glue::Radio_OdometryReading) {
+        self.odometry.update(odo_reading);
+    }
     pub fn update_command(&mut self, command : crate::glue::Radio_Command) {
         self.command.update(command);
     }
@@ -39,6 +58,18 @@ impl Robot {
         self.global_command.update(global_command);
     }
 
+    pub(crate) fn set_motor_verdicts(&mut self, motor_verdicts : [MotorVerdict; 5]) {
+        self.motor_verdicts = motor_verdicts;
+    }
+
+    pub(crate) fn set_orientation(&mut self, orientation : Option<Orientation>) {
+        self.orientation = orientation;
+    }
+
+    pub(crate) fn set_presence(&mut self, presence : Presence) {
+        self.presence = Some(presence);
+    }
+
     pub fn time_since_update(&self) -> Option<std::time::Duration> {
         const infinite_time : std::time::Duration = std::time::Duration::from_secs(300);
         let mut dur = infinite_time;
@@ -57,6 +88,11 @@ impl Robot {
                 dur = d;
             }
         }
+        if let Some(d) = self.odometry.time_since() {
+            if d < dur {
+                dur = d;
+            }
+        }
         if dur < infinite_time {
             Some(dur)
         } else {
@@ -68,6 +104,36 @@ impl Robot {
         self.status_hf.time_since()
     }
 
+    // When the latest PrimaryStatusHF was received
+    pub fn status_hf_timestamp(&self) -> Option<std::time::Instant> {
+        self.status_hf.timestamp()
+    }
+
+    // When the latest PrimaryStatusLF was received
+    pub fn status_lf_timestamp(&self) -> Option<std::time::Instant> {
+        self.status_lf.timestamp()
+    }
+
+    // When the latest echoed command was received
+    pub fn command_timestamp(&self) -> Option<std::time::Instant> {
+        self.command.timestamp()
+    }
+
+    // When the latest echoed global command was received
+    pub fn global_command_timestamp(&self) -> Option<std::time::Instant> {
+        self.global_command.timestamp()
+    }
+
+    // When the latest message of a class was received
+    pub fn last_received(&self, class : MessageClass) -> Option<std::time::Instant> {
+        match class {
+            MessageClass::StatusHF => self.status_hf.timestamp(),
+            MessageClass::StatusLF => self.status_lf.timestamp(),
+            MessageClass::Imu => self.imu_reading.timestamp(),
+            MessageClass::Odometry => self.odometry.timestamp(),
+        }
+    }
+
     pub fn time_since_status_lf_update(&self) -> Option<std::time::Duration> {
         self.status_lf.time_since()
     }
@@ -76,6 +142,10 @@ impl Robot {
         self.imu_reading.time_since()
     }
 
+    pub fn time_since_odometry_update(&self) -> Option<std::time::Duration> {
+        self.odometry.time_since()
+    }
+
     pub fn time_since_command_update(&self) -> Option<std::time::Duration> {
         self.command.time_since()
     }
@@ -84,8 +154,39 @@ impl Robot {
         self.global_command.time_since()
     }
 
+    // Online, Stale or Rejoined as tracked by the Monitor with its PresenceConfig. Robots not
+    // tracked by a Monitor are judged by the default PresenceConfig.
     pub fn is_online(&self) -> bool {
-        self.time_since_update().map_or(false, |time| time < std::time::Duration::from_millis(400)) 
+        match self.presence {
+            Some(presence) => presence.state.is_present(),
+            None => {
+                let received = MessageClass::ALL.map(|class| self.last_received(class));
+                Presence::evaluate(&PresenceConfig::default(), &received, std::time::Instant::now())
+                    .is_some_and(|(state, _)| state.is_present())
+            }
+        }
+    }
+
+    // Snapshot of everything known about the robot in SI units
+    pub fn state(&self) -> RobotState {
+        fn aged<T: Copy>(stamped: &Stamped<T>) -> Option<(std::time::Duration, T)> {
+            match stamped {
+                Stamped::Have(timestamp, value) => Some((timestamp.elapsed(), *value)),
+                Stamped::NothingYet => None,
+            }
+        }
+        RobotState {
+            online: self.is_online(),
+            status_hf: aged(&self.status_hf).map(|(age, status_hf)| StatusHF::decode(age, &status_hf)),
+            status_lf: aged(&self.status_lf).map(|(age, status_lf)| StatusLF::decode(age, &status_lf)),
+            imu: aged(&self.imu_reading).map(|(age, imu)| Imu::decode(age, &imu)),
+            odometry: aged(&self.odometry).map(|(age, odometry)| OdometryState { age, odometry: odometry.into() }),
+            command: aged(&self.command).map(|(age, command)| CommandState { age, command: RobotCommand::decode(&command) }),
+            global_command: aged(&self.global_command).map(|(age, gcommand)| {
+                let (command, heading_last_measurement) = GlobalCommand::decode(&gcommand);
+                GlobalCommandState { age, command, heading_last_measurement }
+            }),
+        }
     }
 
     //* Accessors for various internal bits *//
@@ -100,6 +201,26 @@ impl Robot {
         self.global_command.have(|a| a)
     }
 
+    // Returns an Option containing the raw odometry reading
+    pub fn odometry_reading(&self) -> Option<glue::Radio_OdometryReading> {
+        self.odometry.have(|a| a)
+    }
+
+    // Returns an Option containing the odometry position in m and heading in rad
+    pub fn odometry(&self) -> Option<Odometry> {
+        self.odometry.have(Odometry::from)
+    }
+
+    // Returns an Option containing the odometry position (x, y) in m
+    pub fn odometry_position(&self) -> Option<(f32, f32)> {
+        self.odometry.have(|odometry| {(odometry.pos_x, odometry.pos_y)})
+    }
+
+    // Returns an Option containing the odometry heading in rad
+    pub fn odometry_heading(&self) -> Option<f32> {
+        self.odometry.have(|odometry| {odometry.ang_z})
+    }
+
     // Returns an Option containing the main microcontroller status
     pub fn primary_status(&self) -> Option<glue::HG_Status> {
         self.status_lf.have(|status_lf| {status_lf.primary_status})
@@ -122,7 +243,7 @@ impl Robot {
 
     // Returns an Option containing the kicker capacitor voltage in Volts
     pub fn kicker_cap_voltage(&self) -> Option<f32> {
-        self.status_lf.have(|status_lf| {status_lf.cap_voltage as f32 * glue::Scale_KICKER_VCAP})
+        self.status_lf.have(|status_lf| {scaling::CAP_VOLTAGE.decode(status_lf.cap_voltage)})
     }
 
     pub fn smart_kick_counter(&self) -> Option<u8> {
@@ -157,38 +278,52 @@ impl Robot {
         self.status_lf.have(|status_lf| {status_lf.motor_status[index as usize]})
     }
 
+    // Returns the diagnosis of all 5 motors, from comparing the echoed commands with the measured motor speeds and currents
+    pub fn motor_verdicts(&self) -> [MotorVerdict; 5] {
+        self.motor_verdicts
+    }
+
     // Returns an Option of an array of all 5 motor speeds in rad/s
     pub fn motor_speeds(&self) -> Option<[f32; 5]> {
         self.status_hf.have(|status_hf| {
-            status_hf.motor_speeds_i.map(|x| x as f32 * glue::Scale_WHEEL_SPEED)
+            scaling::MOTOR_SPEED.decode_array(status_hf.motor_speeds_i)
         })
     }
 
+    // Returns an Option of the body velocity (x, y in m/s, w in rad/s) in the robot frame, from the measured wheel speeds
+    // and the given geometry (see MonitorConfig::diagnostics)
+    pub fn measured_body_velocity(&self, kinematics : &Kinematics) -> Option<(f32, f32, f32)> {
+        kinematics.body_velocity(&self.motor_speeds()?)
+    }
+
+    // Returns an Option of the dribbler speed in rad/s, None if motor 4 doesn't drive the dribbler
+    pub fn dribbler_speed(&self, kinematics : &Kinematics) -> Option<f32> {
+        kinematics.dribbler_speed(&self.motor_speeds()?)
+    }
+
     // Returns an Option of an individual motor speed in rad/s (note, use motor_speeds() when multiple motor speeds are required)
     pub fn motor_speed(&self, index : u8) -> Option<f32> {
         if index >= 5 { return None; }
-        self.status_hf.have(|status_hf| {status_hf.motor_speeds_i[index as usize] as f32 * glue::Scale_WHEEL_SPEED})
+        self.status_hf.have(|status_hf| {scaling::MOTOR_SPEED.decode(status_hf.motor_speeds_i[index as usize])})
     }
 
     // Returns an Option of an array of all 5 motor currents in Amperes
     pub fn motor_currents(&self) -> Option<[f32; 5]> {
         self.status_hf.have(|status_hf| {
-            status_hf.motor_currents_i.map(|x| x as f32 * glue::Scale_CURRENT)
+            scaling::MOTOR_CURRENT.decode_array(status_hf.motor_currents_i)
         })
     }
 
     // Returns an Option of an individual motor current in Amperes (note, use motor_currents() when multiple motor speeds are required)
     pub fn motor_current(&self, index : u8) -> Option<f32> {
         if index >= 5 { return None; }
-        self.status_hf.have(|status_hf| {status_hf.motor_currents_i[index as usize] as f32 * glue::Scale_CURRENT})
+        self.status_hf.have(|status_hf| {scaling::MOTOR_CURRENT.decode(status_hf.motor_currents_i[index as usize])})
     }
 
     // Returns an Option of an array of all 5 motor temperatures in degrees Celsius
     pub fn motor_temperatures(&self) -> Option<[f32; 5]> {
         self.status_lf.have(|status_lf| {
-            status_lf.motor_driver_temps.map(|temp| {
-                temp as f32 * glue::Scale_MD_TEMP
-            })
+            scaling::MOTOR_DRIVER_TEMP.decode_array(status_lf.motor_driver_temps)
         })
     }
 
@@ -234,28 +369,28 @@ impl Robot {
     //  Returns an Option containing the main board current in Amperes
     pub fn main_board_current(&self) -> Option<f32> {
         self.status_lf.have(|status_lf| {
-            status_lf.main_board_current as f32 * glue::Scale_CURRENT
+            scaling::MAIN_BOARD_CURRENT.decode(status_lf.main_board_current)
         })
     }
 
     //  Returns an Option containing the average loop time in microseconds
     pub fn avg_loop_time(&self) -> Option<u32> {
         self.status_lf.have(|status_lf| {
-            status_lf.avg_loop_time as u32 * 10
+            scaling::LOOP_TIME.decode(status_lf.avg_loop_time) as u32
         })
     }
 
     //  Returns an Option containing the average loop time in microseconds
     pub fn max_loop_time(&self) -> Option<u32> {
         self.status_lf.have(|status_lf| {
-            status_lf.max_loop_time as u32 * 10
+            scaling::LOOP_TIME.decode(status_lf.max_loop_time) as u32
         })
     }
 
     //  Returns an Option containing the average command time in milliseconds
     pub fn avg_command_time(&self) -> Option<u32> {
         self.status_lf.have(|status_lf| {
-            status_lf.avg_command_time as u32
+            scaling::COMMAND_TIME.decode(status_lf.avg_command_time) as u32
         })
     }
 
@@ -264,6 +399,26 @@ impl Robot {
         self.imu_reading.have(|imu| {imu})
     }
 
+    //  Returns an Option containing the orientation estimated from the imu readings (gyro integrated heading, bias, drift, tilt)
+    pub fn orientation(&self) -> Option<Orientation> {
+        self.orientation
+    }
+
+    //  Returns an Option containing the presence tracked by the Monitor, None without one
+    pub fn presence(&self) -> Option<Presence> {
+        self.presence
+    }
+
+    //  Returns an Option containing the gyro integrated heading in radians
+    pub fn imu_heading(&self) -> Option<f32> {
+        self.orientation.map(|orientation| orientation.heading)
+    }
+
+    //  Returns an Option containing whether the robot is tilted beyond ImuConfig::tilt_angle
+    pub fn tilted(&self) -> Option<bool> {
+        self.orientation.map(|orientation| orientation.tilted)
+    }
+
     pub fn breakbeam_raw(&self) -> Option<u16> {
         self.status_hf.have(|status_hf| {status_hf.breakbeam_raw})
     }
@@ -271,9 +426,7 @@ impl Robot {
     // Returns an Option containing the left (0) and right (1) pack voltages in Volts
     pub fn pack_voltages(&self) -> Option<[f32; 2]> {
         self.status_lf.have(|status_lf| {
-            status_lf.pack_voltages.map(|voltage| {
-                voltage as f32 * glue::Scale_MD_BATV
-            })
+            scaling::PACK_VOLTAGE.decode_array(status_lf.pack_voltages)
         })
     }
 
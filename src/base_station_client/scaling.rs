@@ -0,0 +1,139 @@
+use crate::glue;
+use num_traits::{Bounded, NumCast, ToPrimitive};
+
+// Conversion between a scaled integer field and its physical value: value = raw * factor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scale {
+    pub factor: f32,
+}
+
+// Result of encoding a physical value into a scaled integer field
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Encoded<T> {
+    pub raw: T,
+    // The value did not fit the integer type (or was NaN) and was clamped
+    pub saturated: bool,
+}
+
+impl Scale {
+    pub const fn new(factor: f32) -> Self {
+        Scale { factor }
+    }
+
+    pub fn decode<T: ToPrimitive>(&self, raw: T) -> f32 {
+        raw.to_f32().unwrap_or(0.0) * self.factor
+    }
+
+    pub fn decode_array<T: ToPrimitive + Copy, const N: usize>(&self, raw: [T; N]) -> [f32; N] {
+        raw.map(|r| self.decode(r))
+    }
+
+    // Rounds to the nearest representable value, saturating at the limits of T
+    pub fn encode<T: Bounded + NumCast + ToPrimitive + Copy>(&self, value: f32) -> Encoded<T> {
+        let scaled = (value / self.factor).round();
+        let (min, max) = (T::min_value(), T::max_value());
+        if scaled.is_nan() {
+            return Encoded { raw: NumCast::from(0).unwrap_or(min), saturated: true };
+        }
+        if scaled <= min.to_f32().unwrap_or(f32::MIN) {
+            let exact = scaled == min.to_f32().unwrap_or(f32::MIN);
+            return Encoded { raw: min, saturated: !exact };
+        }
+        match NumCast::from(scaled) {
+            Some(raw) => Encoded { raw, saturated: false },
+            None => Encoded { raw: max, saturated: true },
+        }
+    }
+
+    pub fn encode_array<T: Bounded + NumCast + ToPrimitive + Copy, const N: usize>(&self, values: [f32; N]) -> Encoded<[T; N]> {
+        let encoded = values.map(|v| self.encode::<T>(v));
+        Encoded {
+            raw: encoded.map(|e| e.raw),
+            saturated: encoded.iter().any(|e| e.saturated),
+        }
+    }
+}
+
+//* Radio_PrimaryStatusHF *//
+// motor_speeds_i [rad/s]
+pub const MOTOR_SPEED: Scale = Scale::new(glue::Scale_WHEEL_SPEED);
+// motor_currents_i [A]
+pub const MOTOR_CURRENT: Scale = Scale::new(glue::Scale_CURRENT);
+
+//* Radio_PrimaryStatusLF *//
+// motor_driver_temps [deg C]
+pub const MOTOR_DRIVER_TEMP: Scale = Scale::new(glue::Scale_MD_TEMP);
+// pack_voltages [V]
+pub const PACK_VOLTAGE: Scale = Scale::new(glue::Scale_MD_BATV);
+// cap_voltage [V]
+pub const CAP_VOLTAGE: Scale = Scale::new(glue::Scale_KICKER_VCAP);
+// main_board_current [A]
+pub const MAIN_BOARD_CURRENT: Scale = Scale::new(glue::Scale_CURRENT);
+// avg_loop_time and max_loop_time [us]
+pub const LOOP_TIME: Scale = Scale::new(10.0);
+// avg_command_time [ms]
+pub const COMMAND_TIME: Scale = Scale::new(1.0);
+
+//* Radio_GenericCommand *//
+// dribbler_speed_i [rad/s]
+pub const DRIBBLER_SPEED: Scale = Scale::new(glue::Scale_DRIBBLER_SPEED);
+// kick_time_i [ms]
+pub const KICK_TIME: Scale = Scale::new(glue::Scale_KICK_TIME);
+// time_to_kick [ms]
+pub const TIME_TO_KICK: Scale = Scale::new(glue::Scale_TIME_TO_KICK);
+
+//* Radio_GlobalCommand *//
+// max_yaw_rate [rad/s]
+pub const MAX_YAW_RATE: Scale = Scale::new(glue::Scale_MAX_YAW_RATE);
+
+// Every scaled field, for listing and testing
+pub const ALL: [(&str, Scale); 12] = [
+    ("MOTOR_SPEED", MOTOR_SPEED),
+    ("MOTOR_CURRENT", MOTOR_CURRENT),
+    ("MOTOR_DRIVER_TEMP", MOTOR_DRIVER_TEMP),
+    ("PACK_VOLTAGE", PACK_VOLTAGE),
+    ("CAP_VOLTAGE", CAP_VOLTAGE),
+    ("MAIN_BOARD_CURRENT", MAIN_BOARD_CURRENT),
+    ("LOOP_TIME", LOOP_TIME),
+    ("COMMAND_TIME", COMMAND_TIME),
+    ("DRIBBLER_SPEED", DRIBBLER_SPEED),
+    ("KICK_TIME", KICK_TIME),
+    ("TIME_TO_KICK", TIME_TO_KICK),
+    ("MAX_YAW_RATE", MAX_YAW_RATE),
+];
+
+#[cfg(test)]
+mod scaling_tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for (name, scale) in ALL {
+            for raw in [0u8, 1, 17, 200] {
+                let value = scale.decode(raw);
+                let encoded = scale.encode::<u8>(value);
+                assert_eq!(encoded, Encoded { raw, saturated: false }, "{}", name);
+            }
+            for raw in [i16::MIN, -300, 0, 1234, i16::MAX] {
+                let encoded = scale.encode::<i16>(scale.decode(raw));
+                assert_eq!(encoded, Encoded { raw, saturated: false }, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn saturation() {
+        let scale = Scale::new(0.5);
+        assert_eq!(scale.encode::<u8>(1000.0), Encoded { raw: u8::MAX, saturated: true });
+        assert_eq!(scale.encode::<u8>(-1.0), Encoded { raw: 0, saturated: true });
+        assert_eq!(scale.encode::<i8>(-64.0), Encoded { raw: i8::MIN, saturated: false });
+        assert_eq!(scale.encode::<u32>(f32::INFINITY), Encoded { raw: u32::MAX, saturated: true });
+        assert!(scale.encode::<u16>(f32::NAN).saturated);
+        assert_eq!(scale.encode::<u16>(1.26), Encoded { raw: 3, saturated: false });
+
+        let array = scale.encode_array::<u8, 3>([1.0, 2.0, 500.0]);
+        assert_eq!(array.raw, [2, 4, u8::MAX]);
+        assert!(array.saturated);
+        assert_eq!(scale.decode_array(array.raw), [1.0, 2.0, 127.5]);
+    }
+}
@@ -5,12 +5,20 @@ use std::time::Duration;
 
 const SERIAL_BUF_LEN: usize = 100000;
 
+// Running counts of what came in over the serial port
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SerialStatistics {
+    pub packets: u64,
+    pub crc_errors: u64,
+}
+
 #[derive(Debug)]
 pub struct Serial {
     port : Box<dyn SerialPort>,
     mirror : Option<Box<dyn SerialPort>>,
     serial_buf: Vec<u8>,
     glob_index: usize,
+    statistics: SerialStatistics,
 }
 
 impl Serial {
@@ -22,6 +30,7 @@ impl Serial {
             mirror: None,
             serial_buf: vec![0; SERIAL_BUF_LEN],
             glob_index: 0,
+            statistics: SerialStatistics::default(),
         }.set_dtr())
     }
 
@@ -37,6 +46,10 @@ impl Serial {
         Ok(())
     }
 
+    pub fn statistics(&self) -> SerialStatistics {
+        self.statistics
+    }
+
     pub fn close_mirror(&mut self) {
         self.mirror = None;
     }
@@ -185,6 +198,13 @@ impl Serial {
         self.port.write_all(&bytes)
     }
 
+    // Send an already encoded message, see ProtocolCodec::encode
+    pub fn send_encoded(&mut self, payload : &[u8]) -> Result<(), std::io::Error> {
+        let bytes = crate::glue::bytes_to_packet(payload);
+
+        self.port.write_all(&bytes)
+    }
+
     fn read(&mut self) -> Result<(), ()> {
         // Drop everything coming in on the mirror port
         // if let Some(mirror) = &mut self.mirror {
@@ -278,6 +298,7 @@ impl Serial {
             if crc != crate::glue::crc_calc.checksum(&data) {
                 // Bad packet, skip this start of packet indicator
                 eprintln!("SERIAL: Bad packet: CRC failed");
+                self.statistics.crc_errors += 1;
                 self.serial_buf.rotate_left(index + 1);
                 self.glob_index -= index + 1;
                 return Ok(None);
@@ -285,6 +306,7 @@ impl Serial {
 
             self.serial_buf.rotate_left(index + 3 + len);
             self.glob_index -= index + 3 + len;
+            self.statistics.packets += 1;
 
             return Ok(Some(data));
         } else {
@@ -0,0 +1,303 @@
+use super::basestation::MAX_NUM_ROBOTS;
+use super::robot::Robot;
+use super::scaling;
+use crate::glue::{Radio_GenericCommand, Radio_RobotCommand, Radio_SSL_ID};
+
+// How long after the kick delay a robot may take to report the kick
+pub const SMART_KICK_MARGIN: std::time::Duration = std::time::Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy)]
+pub struct KickParams {
+    // [ms]
+    pub kick_time: f32,
+    pub robot_command: Radio_RobotCommand,
+}
+
+impl KickParams {
+    pub fn new(kick_time: f32, robot_command: Radio_RobotCommand) -> Self {
+        KickParams { kick_time, robot_command }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmartKickOutcome {
+    Pending,
+    // The robot returned the counter with the kick ok flag set
+    Kicked,
+    // The robot returned the counter without the kick ok flag
+    Failed,
+    // The robot didn't return the counter within the delay plus SMART_KICK_MARGIN
+    TimedOut,
+    // Another smart kick was requested for the robot before this one resolved
+    Superseded,
+}
+
+// Returned by Monitor::smart_kick, resolves once the robot reports the kick
+#[derive(Debug, Clone)]
+pub struct SmartKickHandle {
+    id: Radio_SSL_ID,
+    counter: u8,
+    deadline: std::time::Instant,
+    outcome: std::sync::Arc<std::sync::Mutex<SmartKickOutcome>>,
+}
+
+impl SmartKickHandle {
+    pub fn id(&self) -> Radio_SSL_ID {
+        self.id
+    }
+
+    // Value of smart_kick_couter sent for this kick
+    pub fn counter(&self) -> u8 {
+        self.counter
+    }
+
+    pub fn outcome(&self) -> SmartKickOutcome {
+        let Ok(mut outcome) = self.outcome.lock() else { return SmartKickOutcome::Failed; };
+        if *outcome == SmartKickOutcome::Pending && std::time::Instant::now() > self.deadline {
+            *outcome = SmartKickOutcome::TimedOut;
+        }
+        *outcome
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        self.outcome() != SmartKickOutcome::Pending
+    }
+
+    // Block until the kick is resolved
+    pub fn wait(&self) -> SmartKickOutcome {
+        loop {
+            let outcome = self.outcome();
+            if outcome != SmartKickOutcome::Pending {
+                return outcome;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PendingKick {
+    issued: std::time::Instant,
+    kick_time_i: u8,
+    time_to_kick: u8,
+    robot_command: Radio_RobotCommand,
+    handle: SmartKickHandle,
+    // Whether a command with the new counter went out
+    sent: bool,
+}
+
+// Smart kick counters of every robot, shared between the Monitor and its thread
+#[derive(Debug, Default)]
+pub(crate) struct SmartKicks {
+    // Last counter sent to every robot, None before glue sent it a kick
+    counters: [Option<u8>; MAX_NUM_ROBOTS],
+    // Latest smart_kick_counter_return of every robot
+    echoed: [Option<u8>; MAX_NUM_ROBOTS],
+    pending: [Option<PendingKick>; MAX_NUM_ROBOTS],
+}
+
+impl SmartKicks {
+    // None if the robot doesn't exist or the delay doesn't fit time_to_kick
+    pub fn start(&mut self, id: Radio_SSL_ID, params: KickParams, delay: std::time::Duration) -> Option<SmartKickHandle> {
+        let index = id as usize;
+        if index >= MAX_NUM_ROBOTS { return None; }
+        let time_to_kick = scaling::TIME_TO_KICK.encode::<u8>(delay.as_secs_f32() * 1000.0);
+        if time_to_kick.saturated { return None; }
+
+        if let Some(previous) = self.pending[index].take() {
+            previous.resolve(SmartKickOutcome::Superseded);
+        }
+        // Before the first kick the counter continues from what the robot echoes, which can be
+        // left over from an earlier run or another client. 0 is what robots report before their
+        // first smart kick and the echoed value would resolve the kick at once, so both are
+        // skipped. The counter only moves on once a command carrying it was sent, see sent().
+        let next = |c: u8| match c { u8::MAX => 1, c => c + 1 };
+        let mut counter = next(self.last_counter(index));
+        if Some(counter) == self.echoed[index] {
+            counter = next(counter);
+        }
+
+        let issued = std::time::Instant::now();
+        let handle = SmartKickHandle {
+            id,
+            counter,
+            deadline: issued + delay + SMART_KICK_MARGIN,
+            outcome: std::sync::Arc::new(std::sync::Mutex::new(SmartKickOutcome::Pending)),
+        };
+        self.pending[index] = Some(PendingKick {
+            issued,
+            kick_time_i: scaling::KICK_TIME.encode(params.kick_time).raw,
+            time_to_kick: time_to_kick.raw,
+            robot_command: params.robot_command,
+            handle: handle.clone(),
+            sent: false,
+        });
+        Some(handle)
+    }
+
+    // The counter commands repeat, the robot's own before glue sent it a kick
+    fn last_counter(&self, index: usize) -> u8 {
+        self.counters[index].or(self.echoed[index]).unwrap_or(0)
+    }
+
+    // Put the counter into an outgoing command. Until a pending kick was sent, the command
+    // carries its new counter and the kick itself.
+    pub fn apply(&mut self, id: Radio_SSL_ID, gen_command: &mut Radio_GenericCommand) {
+        let index = id as usize;
+        if index >= MAX_NUM_ROBOTS { return; }
+        gen_command.smart_kick_couter = self.last_counter(index);
+        if self.pending[index].as_ref().is_some_and(|kick| kick.handle.is_resolved()) {
+            self.pending[index] = None;
+        }
+        if let Some(kick) = self.pending[index].as_ref().filter(|kick| !kick.sent) {
+            gen_command.smart_kick_couter = kick.handle.counter;
+            gen_command.kick_time_i = kick.kick_time_i;
+            gen_command.time_to_kick = kick.time_to_kick;
+            gen_command.robot_command = kick.robot_command;
+        }
+    }
+
+    // A command prepared by apply() passed the limiter and was queued. If it carried a pending
+    // kick, later commands only repeat the counter.
+    pub fn sent(&mut self, id: Radio_SSL_ID, gen_command: &Radio_GenericCommand) {
+        let Some(Some(kick)) = self.pending.get_mut(id as usize) else { return; };
+        if !kick.sent && gen_command.smart_kick_couter == kick.handle.counter {
+            kick.sent = true;
+            self.counters[id as usize] = Some(kick.handle.counter);
+        }
+    }
+
+    // Resolve the pending kick of a robot from a status received at the given time
+    pub fn update(&mut self, id: Radio_SSL_ID, counter_return: u8, kick_ok: bool, received: std::time::Instant) {
+        let Some(echoed) = self.echoed.get_mut(id as usize) else { return; };
+        *echoed = Some(counter_return);
+        let Some(slot) = self.pending.get_mut(id as usize) else { return; };
+        let Some(kick) = slot else { return; };
+        if !kick.sent || received < kick.issued || counter_return != kick.handle.counter { return; }
+        if let Some(kick) = slot.take() {
+            kick.resolve(if kick_ok { SmartKickOutcome::Kicked } else { SmartKickOutcome::Failed });
+        }
+    }
+
+    pub fn update_from_robots(&mut self, robots: &[Robot; MAX_NUM_ROBOTS]) {
+        for (id, robot) in robots.iter().enumerate() {
+            let (Some(counter_return), Some(kick_ok), Some(received)) =
+                (robot.smart_kick_counter(), robot.kick_ok_flag(), robot.status_hf_timestamp()) else { continue; };
+            self.update(id as Radio_SSL_ID, counter_return, kick_ok, received);
+        }
+    }
+}
+
+impl PendingKick {
+    fn resolve(&self, outcome: SmartKickOutcome) {
+        if let Ok(mut current) = self.handle.outcome.lock() {
+            if *current == SmartKickOutcome::Pending {
+                *current = outcome;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod smart_kick_tests {
+    use super::*;
+    use crate::base_station_client::command::RobotCommand;
+
+    fn params() -> KickParams {
+        KickParams::new(scaling::KICK_TIME.decode(5), Radio_RobotCommand::NONE)
+    }
+
+    #[test]
+    fn counter_and_outcome() {
+        let mut kicks = SmartKicks::default();
+        let mut gen_command = RobotCommand::new().generic_command();
+        kicks.apply(1, &mut gen_command);
+        assert_eq!(gen_command.smart_kick_couter, 0);
+
+        let handle = kicks.start(1, params(), std::time::Duration::from_millis(100)).unwrap();
+        assert_eq!(handle.counter(), 1);
+        kicks.apply(1, &mut gen_command);
+        assert_eq!(gen_command.smart_kick_couter, 1);
+        assert_eq!(gen_command.kick_time_i, 5);
+        assert_eq!(gen_command.time_to_kick, scaling::TIME_TO_KICK.encode::<u8>(100.0).raw);
+
+        // A command the limiter rejected wasn't sent, the next one carries the kick again
+        let mut gen_command = RobotCommand::new().generic_command();
+        kicks.apply(1, &mut gen_command);
+        assert_eq!((gen_command.smart_kick_couter, gen_command.kick_time_i), (1, 5));
+        kicks.update(1, 1, true, std::time::Instant::now());
+        assert_eq!(handle.outcome(), SmartKickOutcome::Pending);
+        kicks.sent(1, &gen_command);
+
+        // Once sent, only the counter is repeated
+        let mut gen_command = RobotCommand::new().generic_command();
+        kicks.apply(1, &mut gen_command);
+        assert_eq!(gen_command.smart_kick_couter, 1);
+        assert_eq!((gen_command.kick_time_i, gen_command.time_to_kick), (0, 0));
+
+        // Old status and other counters don't resolve the kick
+        kicks.update(1, 1, true, std::time::Instant::now() - std::time::Duration::from_secs(1));
+        kicks.update(1, 0, true, std::time::Instant::now());
+        assert_eq!(handle.outcome(), SmartKickOutcome::Pending);
+
+        kicks.update(1, 1, false, std::time::Instant::now());
+        assert_eq!(handle.wait(), SmartKickOutcome::Failed);
+
+        // The counter stays, the kick doesn't
+        let mut gen_command = RobotCommand::new().generic_command();
+        kicks.apply(1, &mut gen_command);
+        assert_eq!(gen_command.smart_kick_couter, 1);
+        assert_eq!(gen_command.kick_time_i, 0);
+    }
+
+    #[test]
+    fn superseded_and_timeout() {
+        let mut kicks = SmartKicks::default();
+        assert!(kicks.start(MAX_NUM_ROBOTS as u8, params(), std::time::Duration::ZERO).is_none());
+        assert!(kicks.start(0, params(), std::time::Duration::from_secs(3600)).is_none());
+
+        let first = kicks.start(0, params(), std::time::Duration::ZERO).unwrap();
+        let mut gen_command = RobotCommand::new().generic_command();
+        kicks.apply(0, &mut gen_command);
+        kicks.sent(0, &gen_command);
+        let second = kicks.start(0, params(), std::time::Duration::ZERO).unwrap();
+        assert_eq!(first.outcome(), SmartKickOutcome::Superseded);
+        assert_eq!(second.counter(), 2);
+        // Superseded before it was sent, the counter is reused
+        let third = kicks.start(0, params(), std::time::Duration::ZERO).unwrap();
+        assert_eq!(second.outcome(), SmartKickOutcome::Superseded);
+        assert_eq!(third.counter(), 2);
+        kicks.apply(0, &mut gen_command);
+        kicks.sent(0, &gen_command);
+        let second = third;
+
+        let timed_out = SmartKickHandle { deadline: std::time::Instant::now(), ..second.clone() };
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        assert_eq!(timed_out.outcome(), SmartKickOutcome::TimedOut);
+        kicks.update(0, 2, true, std::time::Instant::now());
+        assert_eq!(second.outcome(), SmartKickOutcome::TimedOut);
+    }
+
+    #[test]
+    fn continues_from_echoed_counter() {
+        // The robot still echoes 7 from an earlier run
+        let mut kicks = SmartKicks::default();
+        kicks.update(2, 7, true, std::time::Instant::now());
+        let mut gen_command = RobotCommand::new().generic_command();
+        kicks.apply(2, &mut gen_command);
+        assert_eq!(gen_command.smart_kick_couter, 7);
+
+        let handle = kicks.start(2, params(), std::time::Duration::ZERO).unwrap();
+        assert_eq!(handle.counter(), 8);
+        kicks.apply(2, &mut gen_command);
+        kicks.sent(2, &gen_command);
+        kicks.update(2, 7, true, std::time::Instant::now());
+        assert_eq!(handle.outcome(), SmartKickOutcome::Pending);
+
+        // Another client moved the robot's counter on to the one glue would use next
+        kicks.update(2, 9, true, std::time::Instant::now());
+        assert_eq!(handle.outcome(), SmartKickOutcome::Pending);
+        let handle = kicks.start(2, params(), std::time::Duration::ZERO).unwrap();
+        assert_eq!(handle.counter(), 10);
+    }
+}
@@ -0,0 +1,179 @@
+use super::command::{GlobalCommand, RobotCommand};
+use super::odometry::Odometry;
+use super::scaling;
+use crate::glue::{HG_ReflexState, HG_Status, Radio_ImuReadings, Radio_PrimaryStatusHF, Radio_PrimaryStatusLF};
+
+// Everything known about a robot in SI units, see Robot::state. Every group is None until the
+// robot sent it, and carries the time since it was received.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RobotState {
+    pub online: bool,
+    pub status_hf: Option<StatusHF>,
+    pub status_lf: Option<StatusLF>,
+    pub imu: Option<Imu>,
+    pub odometry: Option<OdometryState>,
+    pub command: Option<CommandState>,
+    pub global_command: Option<GlobalCommandState>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatusHF {
+    pub age: std::time::Duration,
+    // [rad/s]
+    pub motor_speeds: [f32; 5],
+    // [A]
+    pub motor_currents: [f32; 5],
+    pub breakbeam_ball_detected: bool,
+    pub breakbeam_sensor_ok: bool,
+    pub breakbeam_raw: u16,
+    pub tof_ball_detected: bool,
+    pub tof_sensor_ok: bool,
+    // Cell of the ToF grid the ball is in, x is -left/+right, y is +forward/-back. None if no
+    // ball is detected. See PossessionConfig for the position in metres.
+    pub tof_xy: Option<(i8, u8)>,
+    pub tof_confidence: u8,
+    pub kick_counter: u8,
+    pub reflex_state: HG_ReflexState,
+    pub smart_kick_counter: u8,
+    pub last_kick_ok: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatusLF {
+    pub age: std::time::Duration,
+    // Left and right [V]
+    pub pack_voltages: [f32; 2],
+    // [deg C]
+    pub motor_temperatures: [f32; 5],
+    // [V]
+    pub cap_voltage: f32,
+    // [A]
+    pub main_board_current: f32,
+    // [us]
+    pub avg_loop_time: f32,
+    // [us]
+    pub max_loop_time: f32,
+    // [ms]
+    pub avg_command_time: f32,
+    pub primary_status: HG_Status,
+    pub kicker_status: HG_Status,
+    pub tof_status: HG_Status,
+    pub imu_status: HG_Status,
+    pub motor_statuses: [HG_Status; 5],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Imu {
+    pub age: std::time::Duration,
+    // (x, y, z) [rad/s]
+    pub angular_velocity: [f32; 3],
+    // (x, y, z) [rad]
+    pub angles: [f32; 3],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OdometryState {
+    pub age: std::time::Duration,
+    pub odometry: Odometry,
+}
+
+// The last command sent to the robot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommandState {
+    pub age: std::time::Duration,
+    pub command: RobotCommand,
+}
+
+// The last global command sent to the robot, in the vision frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalCommandState {
+    pub age: std::time::Duration,
+    pub command: GlobalCommand,
+    // [rad]
+    pub heading_last_measurement: f32,
+}
+
+impl StatusHF {
+    pub fn decode(age: std::time::Duration, status_hf: &Radio_PrimaryStatusHF) -> Self {
+        let flags = &status_hf.__bindgen_anon_1;
+        let tof_ball_detected = flags.tof_ball_detected() && flags.tof_sensor_ok();
+        StatusHF {
+            age,
+            motor_speeds: scaling::MOTOR_SPEED.decode_array(status_hf.motor_speeds_i),
+            motor_currents: scaling::MOTOR_CURRENT.decode_array(status_hf.motor_currents_i),
+            breakbeam_ball_detected: flags.breakbeam_ball_detected() && flags.breakbeam_sensor_ok(),
+            breakbeam_sensor_ok: flags.breakbeam_sensor_ok(),
+            breakbeam_raw: status_hf.breakbeam_raw,
+            tof_ball_detected,
+            tof_sensor_ok: flags.tof_sensor_ok(),
+            tof_xy: tof_ball_detected.then_some((status_hf.tof_ball_x, status_hf.tof_ball_y)),
+            tof_confidence: status_hf.tof_confidence,
+            kick_counter: status_hf.kick_counter,
+            reflex_state: flags.reflex_state(),
+            smart_kick_counter: status_hf.smart_kick_counter_return,
+            last_kick_ok: flags.last_kick_ok(),
+        }
+    }
+}
+
+impl StatusLF {
+    pub fn decode(age: std::time::Duration, status_lf: &Radio_PrimaryStatusLF) -> Self {
+        StatusLF {
+            age,
+            pack_voltages: scaling::PACK_VOLTAGE.decode_array(status_lf.pack_voltages),
+            motor_temperatures: scaling::MOTOR_DRIVER_TEMP.decode_array(status_lf.motor_driver_temps),
+            cap_voltage: scaling::CAP_VOLTAGE.decode(status_lf.cap_voltage),
+            main_board_current: scaling::MAIN_BOARD_CURRENT.decode(status_lf.main_board_current),
+            avg_loop_time: scaling::LOOP_TIME.decode(status_lf.avg_loop_time),
+            max_loop_time: scaling::LOOP_TIME.decode(status_lf.max_loop_time),
+            avg_command_time: scaling::COMMAND_TIME.decode(status_lf.avg_command_time),
+            primary_status: status_lf.primary_status,
+            kicker_status: status_lf.kicker_status,
+            tof_status: status_lf.tof_status,
+            imu_status: status_lf.imu_status,
+            motor_statuses: status_lf.motor_status,
+        }
+    }
+}
+
+impl Imu {
+    pub fn decode(age: std::time::Duration, imu: &Radio_ImuReadings) -> Self {
+        Imu {
+            age,
+            angular_velocity: [imu.ang_wx, imu.ang_wy, imu.ang_wz],
+            angles: [imu.ang_x, imu.ang_y, imu.ang_z],
+        }
+    }
+}
+
+#[cfg(test)]
+mod state_tests {
+    use super::*;
+    use crate::base_station_client::robot::Robot;
+    use crate::glue::{HG_Pose, Radio_RobotCommand};
+
+    #[test]
+    fn snapshot() {
+        let mut robot = Robot::default();
+        assert_eq!(robot.state(), RobotState::default());
+
+        let command = RobotCommand::new().velocity(1.0, 2.0, 3.0).kick_time(scaling::KICK_TIME.decode(4));
+        robot.update_command(command.to_command());
+        robot.update_imu_reading(Radio_ImuReadings { ang_wx: 0.1, ang_wy: 0.2, ang_wz: 0.3, ang_x: 1.0, ang_y: 2.0, ang_z: 3.0 });
+
+        let state = robot.state();
+        assert!(state.online);
+        assert!(state.status_hf.is_none());
+        let imu = state.imu.unwrap();
+        assert_eq!(imu.angles, [1.0, 2.0, 3.0]);
+        assert!(imu.age < std::time::Duration::from_secs(1));
+        assert_eq!(state.command.unwrap().command, command);
+
+        let decoded = RobotCommand::decode(&crate::glue::Radio_Command {
+            speed: HG_Pose { x: 0.5, y: 0.0, z: 0.0 },
+            gen_command: RobotCommand::new().robot_command(Radio_RobotCommand::NONE).generic_command(),
+            _pad: [0; 8],
+        });
+        assert_eq!(decoded, RobotCommand::new().velocity(0.5, 0.0, 0.0));
+    }
+}
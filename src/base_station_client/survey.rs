@@ -0,0 +1,362 @@
+use super::basestation::{Monitor, MAX_NUM_ROBOTS};
+use super::link::LinkMessage;
+use super::serial::SerialStatistics;
+use crate::glue::Radio_SSL_ID;
+
+// Anything that can hop between radio channels and count what it receives
+pub trait SurveyRadio {
+    fn current_channel(&mut self) -> Result<u8, ()>;
+    fn set_channel(&mut self, chan: u8) -> Result<(), ()>;
+    // Listen on the current channel for the given time and count the frames of every robot,
+    // indexed by Radio_SSL_ID
+    fn measure(&mut self, dwell: std::time::Duration) -> [u64; MAX_NUM_ROBOTS];
+    // Running packet and CRC error counts of the serial link to the base station
+    fn serial_statistics(&mut self) -> SerialStatistics;
+}
+
+#[derive(Debug, Clone)]
+pub struct SurveyConfig {
+    pub channels: Vec<u8>,
+    // Time spent listening on each channel
+    pub dwell: std::time::Duration,
+    // Stay on the best channel when done, otherwise return to the original one
+    pub auto_select: bool,
+}
+
+// The base station doesn't report radio frames that fail the radio CRC check, they are simply
+// missing. Only frames from robots count, the base station's own are there on any channel.
+// Corrupted frames that do reach glue fail the serial CRC check and are counted in crc_errors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelSample {
+    pub channel: u8,
+    // false if the radio could not be moved to this channel
+    pub reachable: bool,
+    // Frames received from every robot, indexed by Radio_SSL_ID
+    pub frames: [u64; MAX_NUM_ROBOTS],
+    // Packets that failed the CRC check during the dwell
+    pub crc_errors: u64,
+    // Fraction of the packets received during the dwell that failed the CRC check
+    pub crc_error_rate: f32,
+    pub dwell: std::time::Duration,
+}
+
+impl ChannelSample {
+    // Frames per second received from one robot
+    pub fn robot_frame_rate(&self, id: Radio_SSL_ID) -> f32 {
+        let Some(&frames) = self.frames.get(id as usize) else { return 0.0; };
+        if self.dwell.is_zero() { return 0.0; }
+        frames as f32 / self.dwell.as_secs_f32()
+    }
+
+    // Frames per second received from all robots together
+    pub fn frame_rate(&self) -> f32 {
+        (0..MAX_NUM_ROBOTS).map(|id| self.robot_frame_rate(id as Radio_SSL_ID)).sum()
+    }
+
+    pub fn robots_heard(&self) -> usize {
+        self.frames.iter().filter(|&&frames| frames > 0).count()
+    }
+
+    // Robot frame rate without the share of corrupted packets, zero if the channel couldn't be reached
+    pub fn score(&self) -> f32 {
+        if !self.reachable { return 0.0; }
+        self.frame_rate() * (1.0 - self.crc_error_rate)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SurveyReport {
+    pub samples: Vec<ChannelSample>,
+    // Channel the radio was on before the survey
+    pub original_channel: u8,
+    // Channel the radio was left on
+    pub selected_channel: u8,
+}
+
+impl SurveyReport {
+    // The channel most robots were heard on, and of those the one with the highest score
+    pub fn best(&self) -> Option<ChannelSample> {
+        self.samples.iter()
+            .filter(|s| s.reachable)
+            .copied()
+            .max_by(|a, b| a.robots_heard().cmp(&b.robots_heard()).then(a.score().total_cmp(&b.score())))
+    }
+}
+
+// Step through all configured channels and measure reception on each
+pub fn survey<R: SurveyRadio>(radio: &mut R, config: &SurveyConfig) -> Result<SurveyReport, ()> {
+    let original_channel = radio.current_channel()?;
+    let mut samples = vec![];
+    for &channel in &config.channels {
+        let reachable = radio.set_channel(channel).is_ok();
+        let before = radio.serial_statistics();
+        let frames = if reachable { radio.measure(config.dwell) } else { [0; MAX_NUM_ROBOTS] };
+        let after = radio.serial_statistics();
+        let packets = after.packets.saturating_sub(before.packets);
+        let crc_errors = after.crc_errors.saturating_sub(before.crc_errors);
+        let total = packets + crc_errors;
+        samples.push(ChannelSample {
+            channel,
+            reachable,
+            frames,
+            crc_errors,
+            crc_error_rate: if total == 0 { 0.0 } else { crc_errors as f32 / total as f32 },
+            dwell: config.dwell,
+        });
+    }
+
+    let mut report = SurveyReport { samples, original_channel, selected_channel: original_channel };
+    let target = match (config.auto_select, report.best()) {
+        (true, Some(best)) if best.score() > 0.0 => best.channel,
+        _ => original_channel,
+    };
+    if radio.set_channel(target).is_ok() {
+        report.selected_channel = target;
+    } else if radio.set_channel(original_channel).is_err() {
+        return Err(());
+    }
+    Ok(report)
+}
+
+// Surveys with the real radios. Robots are taken along to each channel, so channels where they
+// don't reappear show up as unreachable.
+pub struct MonitorSurvey<'a> {
+    pub monitor: &'a mut Monitor,
+    pub timeout: std::time::Duration,
+}
+
+impl MonitorSurvey<'_> {
+    // Frames received from every robot since the monitor started
+    fn robot_frames(&self) -> [u64; MAX_NUM_ROBOTS] {
+        std::array::from_fn(|id| {
+            self.monitor.link_quality(id as Radio_SSL_ID).map_or(0, |link| {
+                LinkMessage::ALL.iter().map(|&message| link.get(message).received).sum()
+            })
+        })
+    }
+}
+
+impl SurveyRadio for MonitorSurvey<'_> {
+    fn current_channel(&mut self) -> Result<u8, ()> {
+        self.monitor.read_channel(self.timeout).map_err(|_| ())
+    }
+
+    fn set_channel(&mut self, chan: u8) -> Result<(), ()> {
+        self.monitor.switch_channel(chan, self.timeout).map_err(|_| ())
+    }
+
+    fn measure(&mut self, dwell: std::time::Duration) -> [u64; MAX_NUM_ROBOTS] {
+        let start = self.robot_frames();
+        std::thread::sleep(dwell);
+        let end = self.robot_frames();
+        std::array::from_fn(|id| end[id].saturating_sub(start[id]))
+    }
+
+    fn serial_statistics(&mut self) -> SerialStatistics {
+        self.monitor.serial_statistics()
+    }
+}
+
+impl Monitor {
+    // Survey the configured channels. See survey()
+    pub fn survey_channels(&mut self, config: &SurveyConfig, timeout: std::time::Duration) -> Result<SurveyReport, ()> {
+        survey(&mut MonitorSurvey { monitor: self, timeout }, config)
+    }
+}
+
+// Reception conditions of a simulated channel
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChannelConditions {
+    // Fraction of frames that never arrive, 1.0 for a channel nothing gets through on
+    pub loss: f32,
+    // Fraction of the arriving frames that fail the CRC check
+    pub corruption: f32,
+}
+
+// A robot of a simulated base station
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulatedRobot {
+    pub channel: u8,
+    // Confirms channel writes, but stays where it is
+    pub stuck: bool,
+}
+
+// A base station without radios, for testing the survey and channel switching. Every robot on
+// the same channel as the base station sends frames at a fixed rate, reduced by the per-channel
+// conditions. Channels without conditions are clean.
+#[derive(Debug, Clone)]
+pub struct SimulatedBaseStation {
+    pub channel: u8,
+    // Per robot [Hz]
+    pub frame_rate: f32,
+    pub conditions: std::collections::HashMap<u8, ChannelConditions>,
+    pub robots: std::collections::BTreeMap<Radio_SSL_ID, SimulatedRobot>,
+    // Packets and CRC errors measured so far
+    pub statistics: SerialStatistics,
+    // Robots whose WRITE_RETURN came back after the last channel write
+    pub(crate) returned: Vec<Radio_SSL_ID>,
+}
+
+impl SimulatedBaseStation {
+    pub fn new(channel: u8, frame_rate: f32) -> Self {
+        SimulatedBaseStation {
+            channel,
+            frame_rate,
+            conditions: Default::default(),
+            robots: Default::default(),
+            statistics: SerialStatistics::default(),
+            returned: vec![],
+        }
+    }
+
+    pub fn with_conditions(mut self, channel: u8, conditions: ChannelConditions) -> Self {
+        self.conditions.insert(channel, conditions);
+        self
+    }
+
+    // Adds a robot on the current channel of the base station
+    pub fn with_robot(mut self, id: Radio_SSL_ID) -> Self {
+        self.robots.insert(id, SimulatedRobot { channel: self.channel, stuck: false });
+        self
+    }
+
+    pub fn with_stuck_robot(mut self, id: Radio_SSL_ID) -> Self {
+        self.robots.insert(id, SimulatedRobot { channel: self.channel, stuck: true });
+        self
+    }
+
+    pub fn loss(&self, channel: u8) -> f32 {
+        self.conditions.get(&channel).map_or(0.0, |conditions| conditions.loss.clamp(0.0, 1.0))
+    }
+
+    pub fn corruption(&self, channel: u8) -> f32 {
+        self.conditions.get(&channel).map_or(0.0, |conditions| conditions.corruption.clamp(0.0, 1.0))
+    }
+
+    // Whether frames between the base station and a robot get through
+    pub fn reaches(&self, id: Radio_SSL_ID) -> bool {
+        self.robots.get(&id).is_some_and(|robot| robot.channel == self.channel) && self.loss(self.channel) < 1.0
+    }
+
+    // Sends a channel write to a robot, returns whether its WRITE_RETURN came back
+    pub fn write_robot_channel(&mut self, id: Radio_SSL_ID, chan: u8) -> bool {
+        if !self.reaches(id) { return false; }
+        let Some(robot) = self.robots.get_mut(&id) else { return false; };
+        if !robot.stuck {
+            robot.channel = chan;
+        }
+        // The return is sent on the new channel
+        robot.channel == self.channel
+    }
+}
+
+impl SurveyRadio for SimulatedBaseStation {
+    fn current_channel(&mut self) -> Result<u8, ()> {
+        Ok(self.channel)
+    }
+
+    // The robots on the channel of the base station are taken along, whatever the conditions.
+    // Losing them on a dead channel is left to the channel switching tests.
+    fn set_channel(&mut self, chan: u8) -> Result<(), ()> {
+        for robot in self.robots.values_mut() {
+            if robot.channel == self.channel && !robot.stuck {
+                robot.channel = chan;
+            }
+        }
+        self.channel = chan;
+        Ok(())
+    }
+
+    fn measure(&mut self, dwell: std::time::Duration) -> [u64; MAX_NUM_ROBOTS] {
+        let arriving = self.frame_rate * dwell.as_secs_f32() * (1.0 - self.loss(self.channel));
+        let corrupted = (arriving * self.corruption(self.channel)).round() as u64;
+        let intact = arriving.round() as u64 - corrupted;
+        let frames: [u64; MAX_NUM_ROBOTS] = std::array::from_fn(|id| if self.reaches(id as Radio_SSL_ID) { intact } else { 0 });
+        let heard = frames.iter().filter(|&&frames| frames > 0).count() as u64;
+        self.statistics.packets += intact * heard;
+        self.statistics.crc_errors += corrupted * heard;
+        frames
+    }
+
+    fn serial_statistics(&mut self) -> SerialStatistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod survey_tests {
+    use super::*;
+
+    fn config(auto_select: bool) -> SurveyConfig {
+        SurveyConfig {
+            channels: vec![0, 1, 2, 3],
+            dwell: std::time::Duration::from_secs(2),
+            auto_select,
+        }
+    }
+
+    fn noisy_station() -> SimulatedBaseStation {
+        SimulatedBaseStation::new(0, 50.0)
+            .with_robot(0)
+            .with_robot(3)
+            .with_conditions(0, ChannelConditions { loss: 0.5, ..Default::default() })
+            .with_conditions(1, ChannelConditions { loss: 0.2, ..Default::default() })
+            .with_conditions(2, ChannelConditions { loss: 0.1, ..Default::default() })
+            .with_conditions(3, ChannelConditions { loss: 1.0, ..Default::default() })
+    }
+
+    #[test]
+    fn picks_cleanest_channel() {
+        let mut station = noisy_station();
+        let report = survey(&mut station, &config(true)).unwrap();
+        assert_eq!(report.original_channel, 0);
+        assert_eq!(report.selected_channel, 2);
+        assert_eq!(station.channel, 2);
+        assert!(station.robots.values().all(|robot| robot.channel == 2));
+
+        let sample = report.samples[2];
+        assert_eq!(sample.frames, [90, 0, 0, 90, 0, 0]);
+        assert_eq!(sample.robots_heard(), 2);
+        assert!((sample.robot_frame_rate(3) - 45.0).abs() < 1e-3);
+        assert!((sample.frame_rate() - 90.0).abs() < 1e-3);
+        assert!((report.samples[1].frame_rate() - 80.0).abs() < 1e-3);
+
+        // Nothing gets through on channel 3, but the radio could be moved there
+        assert!(report.samples[3].reachable);
+        assert_eq!(report.samples[3].frames, [0; MAX_NUM_ROBOTS]);
+    }
+
+    #[test]
+    fn returns_to_original_channel_without_auto_select() {
+        let mut station = noisy_station();
+        let report = survey(&mut station, &config(false)).unwrap();
+        assert_eq!(report.best().map(|s| s.channel), Some(2));
+        assert_eq!(report.selected_channel, 0);
+        assert_eq!(station.channel, 0);
+        assert!(station.robots.values().all(|robot| robot.channel == 0));
+    }
+
+    #[test]
+    fn prefers_channels_all_robots_are_heard_on() {
+        let mut station = noisy_station().with_stuck_robot(5);
+        let report = survey(&mut station, &config(true)).unwrap();
+        // Robot 5 stays on channel 0, so that is the only channel all three are heard on
+        assert_eq!(report.samples[0].robots_heard(), 3);
+        assert_eq!(report.selected_channel, 0);
+    }
+
+    #[test]
+    fn corrupted_channels() {
+        // Channel 2 loses fewer frames than channel 1, but a third of what arrives fails the CRC check
+        let mut station = noisy_station().with_conditions(2, ChannelConditions { loss: 0.1, corruption: 1.0 / 3.0 });
+        let report = survey(&mut station, &config(true)).unwrap();
+        let sample = report.samples[2];
+        assert_eq!(sample.frames, [60, 0, 0, 60, 0, 0]);
+        assert_eq!(sample.crc_errors, 60);
+        assert!((sample.crc_error_rate - 1.0 / 3.0).abs() < 1e-3);
+        assert!((sample.score() - 40.0).abs() < 1e-3);
+        assert_eq!(report.samples[1].crc_error_rate, 0.0);
+        assert_eq!(report.selected_channel, 1);
+        assert_eq!(station.statistics.crc_errors, 60);
+    }
+}
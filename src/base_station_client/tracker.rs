@@ -0,0 +1,130 @@
+use super::basestation::MAX_NUM_ROBOTS;
+use super::battery::{BatteryConfig, BatteryTracker};
+use super::diagnostics::{DiagnosticsConfig, DiagnosticsTracker};
+use super::health::{HealthRules, HealthTracker};
+use super::history::{History, HistoryConfig};
+use super::imu::{ImuConfig, Imus};
+use super::kicker::{KickerConfig, KickerTracker};
+use super::latency::{Latencies, LatencyConfig};
+use super::link::{LinkConfig, Links};
+use super::odometry::{AutoOverride, OdometryOverrides, OdometryTolerance};
+use super::possession::{PossessionConfig, PossessionTracker};
+use super::presence::{PresenceConfig, Presences};
+use super::robot::Robot;
+use crate::glue::Radio_SSL_ID;
+
+// Timestamp of the latest message a tracker handled. Robots keep their latest status until a
+// new one arrives, this tells the new ones apart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct LastSeen(Option<std::time::Instant>);
+
+impl LastSeen {
+    // The timestamp of a message if it wasn't handled yet, None if it was or there is none
+    pub fn take_new(&mut self, received: Option<std::time::Instant>) -> Option<std::time::Instant> {
+        let received = received?;
+        if self.0 == Some(received) { return None; }
+        self.0 = Some(received);
+        Some(received)
+    }
+}
+
+// One T per robot, by robot id
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PerRobot<T>([T; MAX_NUM_ROBOTS]);
+
+impl<T> PerRobot<T> {
+    pub fn get(&self, id: Radio_SSL_ID) -> Option<&T> {
+        self.0.get(id as usize)
+    }
+
+    pub fn get_mut(&mut self, id: Radio_SSL_ID) -> Option<&mut T> {
+        self.0.get_mut(id as usize)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Radio_SSL_ID, &T)> {
+        self.0.iter().enumerate().map(|(id, item)| (id as Radio_SSL_ID, item))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Radio_SSL_ID, &mut T)> {
+        self.0.iter_mut().enumerate().map(|(id, item)| (id as Radio_SSL_ID, item))
+    }
+
+    pub fn map<U>(&self, mut f: impl FnMut(&T) -> U) -> [U; MAX_NUM_ROBOTS] {
+        std::array::from_fn(|id| f(&self.0[id]))
+    }
+}
+
+// Follows the statuses of a single robot
+pub(crate) trait RobotTracker: Default {
+    type Config;
+    type Event;
+
+    // Handle the new statuses of a robot, returns the events they caused
+    fn update(&mut self, config: &Self::Config, id: Radio_SSL_ID, robot: &mut Robot) -> Vec<Self::Event>;
+}
+
+impl<T: RobotTracker> PerRobot<T> {
+    pub fn update(&mut self, config: &T::Config, robots: &mut [Robot; MAX_NUM_ROBOTS]) -> Vec<T::Event> {
+        let mut events = vec![];
+        for ((id, tracker), robot) in self.iter_mut().zip(robots.iter_mut()) {
+            events.extend(tracker.update(config, id, robot));
+        }
+        events
+    }
+}
+
+// Everything the monitor thread evaluates robots with, see Monitor::configure
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MonitorConfig {
+    pub battery: BatteryConfig,
+    // Robot geometry and thresholds motors are diagnosed with, see Robot::motor_verdicts
+    pub diagnostics: DiagnosticsConfig,
+    pub possession: PossessionConfig,
+    pub kicker: KickerConfig,
+    pub imu: ImuConfig,
+    // How many samples, and how old, are kept per robot and kind of message
+    pub history: HistoryConfig,
+    // Timeouts per message class after which robots are stale and offline
+    pub presence: PresenceConfig,
+    pub link: LinkConfig,
+    pub latency: LatencyConfig,
+    // Rules robots are evaluated with, changes in health level are sent as events
+    pub health: HealthRules,
+    // How close odometry readings have to be to an override for it to count as applied
+    pub odometry_tolerance: OdometryTolerance,
+    // Override odometry from Monitor::set_vision_pose, None to leave it off
+    pub auto_odometry_override: Option<AutoOverride>,
+}
+
+// The trackers of the monitor thread and their configuration, shared with the Monitor
+#[derive(Debug, Default)]
+pub(crate) struct Trackers {
+    pub config: MonitorConfig,
+    pub batteries: PerRobot<BatteryTracker>,
+    pub diagnostics: PerRobot<DiagnosticsTracker>,
+    pub possessions: PerRobot<PossessionTracker>,
+    pub kickers: PerRobot<KickerTracker>,
+    pub health: HealthTracker,
+    pub imus: Imus,
+    pub history: History,
+    pub presences: Presences,
+    pub links: Links,
+    pub latencies: Latencies,
+    pub odometry_overrides: OdometryOverrides,
+}
+
+#[cfg(test)]
+mod tracker_tests {
+    use super::*;
+
+    #[test]
+    fn last_seen() {
+        let start = std::time::Instant::now();
+        let later = start + std::time::Duration::from_millis(10);
+        let mut last_seen = LastSeen::default();
+        assert_eq!(last_seen.take_new(None), None);
+        assert_eq!(last_seen.take_new(Some(start)), Some(start));
+        assert_eq!(last_seen.take_new(Some(start)), None);
+        assert_eq!(last_seen.take_new(Some(later)), Some(later));
+    }
+}
@@ -13,6 +13,13 @@ impl<T: Copy> Stamped<T> {
         *self = Self::Have(std::time::Instant::now(), val)
     }
 
+    pub fn timestamp(&self) -> Option<std::time::Instant> {
+        match self {
+            Self::NothingYet => None,
+            Self::Have(timestamp, _) => Some(*timestamp),
+        }
+    }
+
     pub fn time_since(&self) -> Option<std::time::Duration> {
         match self {
             Self::NothingYet => None,
@@ -48,5 +55,7 @@ mod stamped_tests {
         } else {
             assert!(false);
         }
+        assert!(var.timestamp().is_some());
+        assert!(Stamped::<u8>::NothingYet.timestamp().is_none());
     }
 }
@@ -0,0 +1,123 @@
+use super::codec::codec_for;
+use crate::glue::{HG_Version, CONST_PROTOCOL_VERSION_MAJOR, CONST_PROTOCOL_VERSION_MINOR};
+
+// What to do when the base station speaks a different protocol version than glue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VersionPolicy {
+    // Refuse to send anything until a matching version has been seen
+    Refuse,
+    // Report the mismatch through Monitor::has_error, but carry on
+    #[default]
+    Warn,
+    // Carry on silently
+    Allow,
+}
+
+// Result of checking the first Base_Information after connecting
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ProtocolHandshake {
+    // No Base_Information received yet
+    #[default]
+    Pending,
+    Matched(HG_Version),
+    // Different version, but this build has a codec for it
+    Translated(HG_Version),
+    Mismatched(HG_Version),
+}
+
+impl ProtocolHandshake {
+    pub fn check(version: HG_Version) -> Self {
+        if version.protcol_version_matches() {
+            ProtocolHandshake::Matched(version)
+        } else if codec_for(version.protocols_major, version.protocols_minor).is_some() {
+            ProtocolHandshake::Translated(version)
+        } else {
+            ProtocolHandshake::Mismatched(version)
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        matches!(self, ProtocolHandshake::Pending)
+    }
+
+    pub fn is_mismatched(&self) -> bool {
+        matches!(self, ProtocolHandshake::Mismatched(_))
+    }
+
+    // Version reported by the base station, if any
+    pub fn version(&self) -> Option<HG_Version> {
+        match *self {
+            ProtocolHandshake::Pending => None,
+            ProtocolHandshake::Matched(version)
+            | ProtocolHandshake::Translated(version)
+            | ProtocolHandshake::Mismatched(version) => Some(version),
+        }
+    }
+
+    // Whether messages may be sent to the base station under the given policy
+    pub fn allows_sending(&self, policy: VersionPolicy) -> bool {
+        match policy {
+            VersionPolicy::Refuse => matches!(self, ProtocolHandshake::Matched(_) | ProtocolHandshake::Translated(_)),
+            VersionPolicy::Warn | VersionPolicy::Allow => true,
+        }
+    }
+}
+
+// Protocol version glue was built against, formatted like HG_Version::protocol_version_to_string
+pub fn glue_protocol_version_string() -> String {
+    format!("#{}.{}", CONST_PROTOCOL_VERSION_MAJOR, CONST_PROTOCOL_VERSION_MINOR)
+}
+
+pub fn mismatch_message(version: &HG_Version) -> String {
+    format!(
+        "Protocol version mismatch: base station {} uses protocol {}, glue was built for protocol {}",
+        version.version_to_string(),
+        version.protocol_version_to_string(),
+        glue_protocol_version_string(),
+    )
+}
+
+// Shared between the monitor and its background thread
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct VersionGuard {
+    pub policy: VersionPolicy,
+    pub handshake: ProtocolHandshake,
+}
+
+#[cfg(test)]
+mod version_tests {
+    use super::*;
+
+    fn version(protocols_major: u8, protocols_minor: u8) -> HG_Version {
+        HG_Version {
+            major: 1,
+            minor: 2,
+            patch: 3,
+            protocols_major,
+            protocols_minor,
+        }
+    }
+
+    #[test]
+    fn policies() {
+        let matched = ProtocolHandshake::check(version(CONST_PROTOCOL_VERSION_MAJOR, CONST_PROTOCOL_VERSION_MINOR));
+        let mismatched = ProtocolHandshake::check(version(CONST_PROTOCOL_VERSION_MAJOR.wrapping_add(1), 0));
+        assert!(!matched.is_mismatched());
+        assert!(mismatched.is_mismatched());
+
+        assert!(matched.allows_sending(VersionPolicy::Refuse));
+        assert!(!mismatched.allows_sending(VersionPolicy::Refuse));
+        assert!(!ProtocolHandshake::Pending.allows_sending(VersionPolicy::Refuse));
+        assert!(mismatched.allows_sending(VersionPolicy::Warn));
+        assert!(mismatched.allows_sending(VersionPolicy::Allow));
+    }
+
+    #[test]
+    fn message_names_both_versions() {
+        let v = version(CONST_PROTOCOL_VERSION_MAJOR.wrapping_add(1), 7);
+        let message = mismatch_message(&v);
+        assert!(message.contains(&v.version_to_string()));
+        assert!(message.contains(&v.protocol_version_to_string()));
+        assert!(message.contains(&glue_protocol_version_string()));
+    }
+}
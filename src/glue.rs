@@ -5,6 +5,11 @@ pub const crc_calc: crc::Crc<u8> = crc::Crc::<u8>::new(&crc::CRC_8_SMBUS);
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
+// Bindings of older protocol revisions, one module per extern/Firmware_Protocols_v<major>_<minor>
+pub mod protocols {
+    include!(concat!(env!("OUT_DIR"), "/protocols.rs"));
+}
+
 pub enum Radio_Message_Rust {
     Command(Radio_Command),
     GlobalCommand(Radio_GlobalCommand),
@@ -193,8 +198,15 @@ impl Radio_Message_Rust {
                     return Radio_Message_Rust::OdometryReading(msg.msg.odo)
                 },
                 Radio_MessageType::MultiConfigMessage => {
-                    // Temporarily off due to unsafe/segfault issues
-                    return Radio_Message_Rust::None
+                    // Catch bad values in the raw bytes, an invalid enum is undefined behaviour as soon
+                    // as it's read out of the union as a Radio_MultiConfigMessage (what used to segfault)
+                    let raw: [u8; std::mem::size_of::<Radio_MultiConfigMessage>()] =
+                        std::ptr::read_unaligned(std::ptr::addr_of!(msg.msg.mcm).cast());
+                    if crate::glue::HG_ConfigOperation::from_u8(raw[std::mem::offset_of!(Radio_MultiConfigMessage, operation)]).is_none() { return Radio_Message_Rust::None; };
+                    if crate::glue::HG_VariableType::from_u8(raw[std::mem::offset_of!(Radio_MultiConfigMessage, type_)]).is_none() { return Radio_Message_Rust::None; };
+                    let vars = std::mem::offset_of!(Radio_MultiConfigMessage, vars);
+                    if raw[vars..vars + std::mem::size_of::<[HG_Variable; 5]>()].iter().any(|&var| crate::glue::HG_Variable::from_u8(var).is_none()) { return Radio_Message_Rust::None; };
+                    Radio_Message_Rust::MultiConfigMessage(std::ptr::read_unaligned(raw.as_ptr().cast::<Radio_MultiConfigMessage>()))
                 },
                 Radio_MessageType::OverrideOdometry => {
                     return Radio_Message_Rust::OverrideOdometry(msg.msg.over_odo)
@@ -224,13 +236,16 @@ impl Radio_Message_Rust {
 
 
 pub fn to_packet<T: zerocopy::AsBytes>(data : T) -> Vec<u8> {
-    let raw_data = data.as_bytes();
+    bytes_to_packet(data.as_bytes())
+}
 
+// Frame raw bytes with a start byte, length and CRC
+pub fn bytes_to_packet(raw_data : &[u8]) -> Vec<u8> {
     let mut a = vec![0; raw_data.len() + 3];
     a[0] = 0b10100101;
     a[1] = raw_data.len() as u8;
-    a[2..raw_data.len()+2].copy_from_slice(&raw_data);
-    a[raw_data.len()+2] = crc_calc.checksum(&raw_data);
+    a[2..raw_data.len()+2].copy_from_slice(raw_data);
+    a[raw_data.len()+2] = crc_calc.checksum(raw_data);
     a
 }
 
@@ -5,12 +5,39 @@
 mod base_station_client;
 mod glue;
 
+pub use base_station_client::scaling;
+
 // Only export stuff that's useful to the outside world
-pub use base_station_client::basestation::{BaseStation, Debug, Monitor, MAX_NUM_ROBOTS};
+pub use base_station_client::config_profile::{ConfigProfile, ProfileDifference, ProfileError, PROFILE_FORMAT_VERSION};
+pub use base_station_client::battery::{BatteryConfig, BatteryEstimate, BatteryModel, BatteryWarning, BatteryWarningEvent, Chemistry, PackLayout};
+pub use base_station_client::basestation::{BaseStation, Debug, Monitor, MAX_NUM_ROBOTS, POLL_INTERVAL};
+pub use base_station_client::channel::{switch_channel, ChannelRadio, ChannelSwitchError};
+pub use base_station_client::command::{GlobalCommand, RobotCommand};
+pub use base_station_client::diagnostics::{DiagnosticsConfig, MotorDiagnostics, MotorVerdict, MotorVerdictEvent, DIAGNOSED_MOTORS};
+pub use base_station_client::events::Event;
+pub use base_station_client::field::{normalize_angle, FieldFrame, FieldSide, RotationDirection};
+pub use base_station_client::health::{Component, Health, HealthAlert, HealthLevel, HealthReason, HealthRules, Threshold, HEALTH_INTERVAL};
+pub use base_station_client::history::{HistoryConfig, HistorySample, RobotHistory, Series};
+pub use base_station_client::imu::{ImuConfig, ImuEstimator, ImuEvent, ImuEventKind, ImuSample, Orientation};
+pub use base_station_client::kicker::{KickEvent, KickSource, KickerConfig, KickerEstimate, KickerModel};
+pub use base_station_client::kinematics::Kinematics;
+pub use base_station_client::link::{LinkConfig, LinkMessage, LinkQuality, MessageLinkStats, RobotLink, GAP_BUCKETS};
+pub use base_station_client::latency::{CommandTag, LatencyConfig, LatencyStats, RoundTrips};
+pub use base_station_client::limits::{Checked, CommandLimiter, CommandLimits, Limit, LimitKind, LimitPolicy, LimitViolation};
+pub use base_station_client::codec::{codec_for, supported_protocol_versions, CurrentCodec, ProtocolCodec, WireLayout};
+pub use base_station_client::odometry::{AutoOverride, Odometry, OdometrySample, OdometryOverride, OdometryTolerance, OverrideOutcome, OverrideStatus, OVERRIDE_VERIFY_WINDOW};
+pub use base_station_client::possession::{BallPossession, BallReading, BallSensor, PossessionChange, PossessionConfig, PossessionEstimate, PossessionEvent, PossessionState};
+pub use base_station_client::presence::{MessageClass, Presence, PresenceConfig, PresenceEvent, PresenceState, Timeouts};
+pub use base_station_client::reflex::{ReflexKickEvent, ReflexKickStatus, ReflexPhase, ReflexTransition, ReportedReflex, REFLEX_HISTORY_LEN};
 pub use base_station_client::robot::Robot;
-pub use base_station_client::serial::Serial;
+pub use base_station_client::state::{CommandState, GlobalCommandState, Imu, OdometryState, RobotState, StatusHF, StatusLF};
+pub use base_station_client::smart_kick::{KickParams, SmartKickHandle, SmartKickOutcome, SMART_KICK_MARGIN};
+pub use base_station_client::serial::{Serial, SerialStatistics};
+pub use base_station_client::survey::{survey, ChannelConditions, ChannelSample, SimulatedBaseStation, SimulatedRobot, SurveyConfig, SurveyRadio, SurveyReport};
+pub use base_station_client::tracker::MonitorConfig;
 pub use base_station_client::utils::Stamped;
-pub use glue::{HG_Status, HG_ReflexState, Radio_Message_Rust, Radio_Command, Radio_GlobalCommand, HG_Pose, Radio_MessageWrapper, Radio_ImuReadings, Radio_RobotCommand, Base_Information, Radio_OverrideOdometry, Radio_SerialMessage, Radio_GenericCommand, Radio_MultiConfigMessage, Radio_SSL_ID, Radio_Broadcast_ID, Radio_BaseStation_ID, HG_Variable, HG_ConfigOperation, HG_VariableType};
+pub use base_station_client::version::{mismatch_message, ProtocolHandshake, VersionPolicy};
+pub use glue::{HG_Status, HG_ReflexState, Radio_Message_Rust, Radio_Command, Radio_GlobalCommand, HG_Pose, Radio_MessageWrapper, Radio_ImuReadings, Radio_OdometryReading, Radio_RobotCommand, Base_Information, Radio_OverrideOdometry, Radio_SerialMessage, Radio_GenericCommand, Radio_MultiConfigMessage, Radio_SSL_ID, Radio_Broadcast_ID, Radio_BaseStation_ID, HG_Variable, HG_ConfigOperation, HG_VariableType};
 
 
 
@@ -107,6 +134,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn unwrap_rejects_garbage_config_message() {
+        use zerocopy::AsBytes;
+
+        let mcm = glue::Radio_MultiConfigMessage::write().add(glue::HG_Variable::KP, 5);
+        let wrapper = glue::Radio_MessageWrapper { id: 0, _pad: [0; 3], msg: glue::Radio_Message_Rust::MultiConfigMessage(mcm).wrap() };
+        let good = wrapper.as_bytes().to_vec();
+        let parsed = glue::Radio_MessageWrapper::from_bytes(good.clone()).expect("valid frame should parse");
+        assert!(matches!(glue::Radio_Message_Rust::unwrap(parsed.msg), glue::Radio_Message_Rust::MultiConfigMessage(_)));
+
+        // Garbage in the operation, the variable type and a variable id
+        let base = &wrapper as *const _ as usize;
+        let offsets = unsafe {[
+            std::ptr::addr_of!(wrapper.msg.msg.mcm.operation) as usize,
+            std::ptr::addr_of!(wrapper.msg.msg.mcm.type_) as usize,
+            std::ptr::addr_of!(wrapper.msg.msg.mcm.vars) as usize + 3,
+        ]};
+        for offset in offsets {
+            let mut bad = good.clone();
+            bad[offset - base] = 0xAB;
+            let parsed = glue::Radio_MessageWrapper::from_bytes(bad).expect("frame length/type still valid");
+            assert!(matches!(glue::Radio_Message_Rust::unwrap(parsed.msg), glue::Radio_Message_Rust::None));
+        }
+    }
+
     // #[test]
     // fn hg_status_conversions() {
     //     let status: HG_Status = HG_Status::OK;